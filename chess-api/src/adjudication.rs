@@ -0,0 +1,158 @@
+/*
+Adjudication for engine and tournament games: ending a game early from engine evaluations or an
+endgame tablebase, instead of playing it out.
+
+- Draw: the evaluations of the last `draw_moves` moves (two plies each) were all made from move
+  `draw_from_move` on, and are all within `draw_threshold` of 0.
+- Win: the evaluations of the last `resign_moves` moves (two plies each) are all at least
+  `resign_threshold` for the same side.
+- Tablebase: standard chess positions without castling rights and with at most `tablebase_pieces`
+  pieces (kings included) are settled by a Tablebase. Cursed wins and blessed losses (decided only
+  after the fifty-move rule) are draws.
+
+Evaluations are in centipawns from white's point of view, one for every ply (either side's move),
+added with Adjudicator::add_eval() together with the position they evaluate. They are kept by ply:
+after a takeback, evaluations of the taken back moves no longer count and are replaced by the next
+ones added. Game::adjudicate() ends the game when the rules decide it; the Outcome's termination
+tells which rule did, and the PGN Termination tag is "adjudication".
+
+The crate doesn't read Syzygy files itself: a prober for the local tables implements Tablebase.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::state::{Color, GameState};
+use crate::variant::{Standard, Variant};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AdjudicationRules {
+    pub draw_from_move: u32,
+    pub draw_moves: usize,
+    pub draw_threshold: i32,
+    pub resign_moves: usize,
+    pub resign_threshold: i32,
+    pub tablebase_pieces: u32, // 0: no tablebase adjudication
+}
+
+impl Default for AdjudicationRules {
+    // Draw after move 40 with 4 moves evaluated within 0.10, win after 3 moves evaluated at
+    // 10.00 or more, tablebases for 6 pieces or fewer.
+    fn default() -> Self {
+        Self {
+            draw_from_move: 40,
+            draw_moves: 4,
+            draw_threshold: 10,
+            resign_moves: 3,
+            resign_threshold: 1000,
+            tablebase_pieces: 6,
+        }
+    }
+}
+
+// A tablebase result for the side to move.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss, // lost, but the fifty-move rule saves it
+    Draw,
+    CursedWin, // won, but not within the fifty-move rule
+    Win,
+}
+
+pub trait Tablebase {
+    // The result of state, None if it isn't in the tables.
+    fn probe_wdl(&self, state: &GameState) -> Option<Wdl>;
+}
+
+pub struct Adjudicator {
+    pub rules: AdjudicationRules,
+    evals: Vec<(u32, i32)>, // (ply of the evaluated position, evaluation), by ply
+    tablebase: Option<Box<dyn Tablebase>>,
+}
+
+impl Adjudicator {
+
+    pub fn new(rules: AdjudicationRules) -> Self {
+        Self { rules, evals: Vec::new(), tablebase: None }
+    }
+
+    pub fn with_tablebase(mut self, tablebase: Box<dyn Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    // The evaluation of state, the position after the latest move. Evaluations of that ply or
+    // later ones, left over from moves that were taken back, are dropped.
+    pub fn add_eval(&mut self, state: &GameState, eval: i32) {
+        let ply = ply(state);
+        self.evals.retain(|&(eval_ply, _)| eval_ply < ply);
+        self.evals.push((ply, eval));
+    }
+
+    // Forget the evaluations, eg. for the next game.
+    pub fn clear(&mut self) {
+        self.evals.clear();
+    }
+
+    // How the rules end the game in state, a position of `variant`, None if they don't (yet).
+    pub fn verdict(&self, state: &GameState, variant: &dyn Variant) -> Option<Outcome> {
+
+        let rules = &self.rules;
+        let pieces = (state.board.white_occupied | state.board.black_occupied).count_ones();
+        let castling = state.white_can_castle_kingside || state.white_can_castle_queenside
+            || state.black_can_castle_kingside || state.black_can_castle_queenside;
+
+        /* the tables are for standard chess without castling */
+        if let Some(tablebase) = &self.tablebase
+            && pieces <= rules.tablebase_pieces
+            && !castling
+            && variant.name() == Standard.name()
+        {
+            let side = state.side_to_move;
+            match tablebase.probe_wdl(state) {
+                Some(Wdl::Win) => return Some(Outcome::win(side, Termination::TablebaseAdjudication)),
+                Some(Wdl::Loss) => return Some(Outcome::win(side.opposite(), Termination::TablebaseAdjudication)),
+                Some(_) => return Some(Outcome::draw(Termination::TablebaseAdjudication)),
+                None => {}
+            }
+        }
+
+        if let Some(evals) = self.last_evals(state, 2 * rules.resign_moves) {
+            if evals.iter().all(|&(_, eval)| eval >= rules.resign_threshold) {
+                return Some(Outcome::win(Color::White, Termination::AdjudicationWin));
+            }
+            if evals.iter().all(|&(_, eval)| eval <= rules.resign_threshold.saturating_neg()) {
+                return Some(Outcome::win(Color::Black, Termination::AdjudicationWin));
+            }
+        }
+
+        /* the first position of move M is the one after white's move */
+        let draw_from = 2 * rules.draw_from_move.max(1) - 1;
+
+        if let Some(evals) = self.last_evals(state, 2 * rules.draw_moves)
+            && evals.iter().all(|&(ply, eval)| ply >= draw_from && eval.saturating_abs() <= rules.draw_threshold)
+        {
+            return Some(Outcome::draw(Termination::AdjudicationDraw));
+        }
+
+        None
+    }
+
+    // The last n evaluations up to state, None if there are fewer (or n is 0, the rule is off).
+    fn last_evals(&self, state: &GameState, n: usize) -> Option<&[(u32, i32)]> {
+        let ply = ply(state);
+        let evals = &self.evals[..self.evals.partition_point(|&(eval_ply, _)| eval_ply <= ply)];
+        if n == 0 || evals.len() < n {
+            return None;
+        }
+        Some(&evals[evals.len() - n..])
+    }
+}
+
+// Plies played before state, counted from the start of the game.
+fn ply(state: &GameState) -> u32 {
+    let black = match state.side_to_move {
+        Color::White => 0,
+        Color::Black => 1,
+    };
+    2 * (state.fullmove_number.max(1) - 1) + black
+}
@@ -0,0 +1,108 @@
+/*
+Antichess (losing chess): the aim is to lose all your pieces.
+
+- Capturing is compulsory: if a capture is possible, only captures may be played.
+- The king is an ordinary piece. There is no check, the king can be captured, and a side can
+  have no king at all (or several, pawns may also promote to a king).
+- There is no castling.
+- A player with no pieces left, or with no legal moves (stalemated), wins.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{generate_pseudo_legal_moves, is_capture, is_castling_move, Move};
+use crate::state::{Color, GameState};
+use crate::variant::Variant;
+
+pub struct Antichess;
+
+impl Variant for Antichess {
+
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn start_position(&self) -> GameState {
+        GameState {
+            white_can_castle_kingside: false,
+            white_can_castle_queenside: false,
+            black_can_castle_kingside: false,
+            black_can_castle_queenside: false,
+            ..GameState::new()
+        }
+    }
+
+    // every promotion can also be to a king
+    fn extra_moves(&self, state: &GameState) -> Vec<Move> {
+        generate_pseudo_legal_moves(state).iter()
+            .filter(|mv| mv.promotion == Some('Q'))
+            .map(|mv| Move::with_promotion(mv.from, mv.to, 'K'))
+            .collect()
+    }
+
+    fn in_check(&self, _state: &GameState, _side: Color) -> bool {
+        false
+    }
+
+    fn is_legal_move(&self, before: &GameState, _after: &GameState, mv: &Move) -> bool {
+        !is_castling_move(mv.from, mv.to, before)
+    }
+
+    // captures are compulsory
+    fn filter_moves(&self, state: &GameState, moves: Vec<Move>) -> Vec<Move> {
+
+        let captures: Vec<Move> = moves.iter()
+            .filter(|mv| is_capture(mv.from, mv.to, state))
+            .copied()
+            .collect();
+
+        if captures.is_empty() {
+            return moves;
+        }
+        captures
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, mv: &Move) {
+
+        // promote() only knows the standard pieces, so the pawn is still on the last rank
+        if mv.promotion == Some('K') {
+
+            let target_mask: u64 = 1<<mv.to;
+            let board = &mut after.board;
+
+            match before.side_to_move {
+                Color::White => {
+                    board.white_pawns &= !target_mask;
+                    board.white_king |= target_mask;
+                }
+                Color::Black => {
+                    board.black_pawns &= !target_mask;
+                    board.black_king |= target_mask;
+                }
+            }
+        }
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        let pieces = match side {
+            Color::White => state.board.white_occupied,
+            Color::Black => state.board.black_occupied,
+        };
+
+        if pieces == 0 {
+            return Some(Outcome::win(side, Termination::AllPiecesLost));
+        }
+        if legal_moves.is_empty() {
+            return Some(Outcome::win(side, Termination::Stalemate));
+        }
+
+        None
+    }
+
+    // any material can be lost
+    fn can_win(&self, _state: &GameState, _side: Color) -> bool {
+        true
+    }
+}
@@ -0,0 +1,120 @@
+/*
+Atomic chess: every capture is an explosion. The capturing piece, the captured piece and all
+pieces except pawns on the squares around the capture square are removed from the board.
+
+- Kings can't capture (they would explode themselves).
+- A move that explodes the mover's own king is illegal.
+- A move that explodes the enemy king wins at once, even if the mover's king is left in check.
+- Kings may stand next to each other. While they do, neither is in check: capturing the king
+  would also explode the capturer's own king.
+
+Exploded pieces are simply gone from the GameState after the move, undoing it goes back to the
+GameState saved in History, which still has them.
+*/
+
+use crate::bitboards::Board;
+use crate::legal_moves::king_pattern;
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{empty_square, is_capture, is_check, update_castling_rights, Move};
+use crate::state::{Color, GameState};
+use crate::variant::Variant;
+
+pub struct Atomic;
+
+impl Variant for Atomic {
+
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn in_check(&self, state: &GameState, side: Color) -> bool {
+
+        let (king, enemy_king) = kings(state, side);
+
+        if king_pattern(king, &state.board.geometry) & enemy_king != 0 {
+            return false;
+        }
+
+        is_check(state, side.opposite())
+    }
+
+    fn is_legal_move(&self, before: &GameState, after: &GameState, mv: &Move) -> bool {
+
+        let side = before.side_to_move;
+
+        let is_king = before.board.piece_at(mv.from) == Some((side, 'K'));
+        if is_king && is_capture(mv.from, mv.to, before) {
+            return false;
+        }
+
+        let (king, enemy_king) = kings(after, side);
+
+        if king == 0 {
+            return false;
+        }
+        if enemy_king == 0 {
+            return true;
+        }
+
+        !self.in_check(after, side)
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, mv: &Move) {
+
+        if !is_capture(mv.from, mv.to, before) {
+            return;
+        }
+
+        let exploded = explosion_mask(mv.to, &after.board);
+
+        for square in 0..64 {
+            if exploded & 1<<square != 0 {
+                update_castling_rights(square, after);
+            }
+        }
+
+        empty_square(exploded, &mut after.board);
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+        let (king, _) = kings(state, side);
+
+        if king == 0 {
+            return Some(Outcome::win(side.opposite(), Termination::KingExploded));
+        }
+
+        if legal_moves.is_empty() {
+            if self.in_check(state, side) {
+                return Some(Outcome::win(side.opposite(), Termination::Checkmate));
+            }
+            return Some(Outcome::draw(Termination::Stalemate));
+        }
+
+        let board = &state.board;
+        if board.white_occupied == board.white_king && board.black_occupied == board.black_king {
+            return Some(Outcome::draw(Termination::InsufficientMaterial));
+        }
+
+        None
+    }
+}
+
+// The squares cleared by a capture on `square`: the square itself and every piece except pawns
+// on the squares a king could move to from there.
+pub fn explosion_mask(square: i8, board: &Board) -> u64 {
+
+    let centre: u64 = 1<<square;
+    let pieces = (board.white_occupied | board.black_occupied) & !(board.white_pawns | board.black_pawns);
+
+    centre | (king_pattern(centre, &board.geometry) & pieces)
+}
+
+// side's king and the enemy king
+fn kings(state: &GameState, side: Color) -> (u64, u64) {
+    match side {
+        Color::White => (state.board.white_king, state.board.black_king),
+        Color::Black => (state.board.black_king, state.board.white_king),
+    }
+}
@@ -13,9 +13,17 @@ In example, the right-most bit (bit 0) represents the square a1. Bit 59 is d8, b
 
 The occupation of the squares is initialized based on the starting position (const) of a chess game.
 
+Smaller boards (minichess) use the squares from a1 up to their last file and rank, described by
+Board.geometry (struct Geometry). The other bits are never set, and the move generation masks its
+targets with Geometry::squares().
+
 
 */
 
+use crate::fairy::{FAIRY_COUNT, FAIRY_PIECES};
+use crate::legal_moves::{BLACK_DOUBLE_PUSH, WHITE_DOUBLE_PUSH};
+use crate::state::Color;
+
 // configuration of pieces in starting position, used to initialize Board struct
 
 const WHITE_PAWN_INIT: u64 = 0b0000000000000000000000000000000000000000000000001111111100000000;
@@ -36,6 +44,53 @@ const WHITE_OCCUPIED: u64 = 0b00000000000000000000000000000000000000000000000011
 const BLACK_OCCUPIED: u64 = 0b1111111111111111000000000000000000000000000000000000000000000000;
 
 
+// The size of the board, and the squares pawns may move two squares from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Geometry {
+    pub files: i8,
+    pub ranks: i8,
+    pub white_double_push: u64,
+    pub black_double_push: u64,
+}
+
+impl Geometry {
+
+    pub const STANDARD: Geometry = Geometry {
+        files: 8,
+        ranks: 8,
+        white_double_push: WHITE_DOUBLE_PUSH,
+        black_double_push: BLACK_DOUBLE_PUSH,
+    };
+
+    // A files x ranks board in the a1 corner, without double pawn pushes.
+    pub fn new(files: i8, ranks: i8) -> Self {
+        if files == 8 && ranks == 8 {
+            return Geometry::STANDARD;
+        }
+        Geometry { files, ranks, white_double_push: 0, black_double_push: 0 }
+    }
+
+    // All squares on the board.
+    pub fn squares(&self) -> u64 {
+        let rank: u64 = (1 << self.files) - 1;
+        (0..self.ranks).fold(0, |squares, i| squares | rank << (i * 8))
+    }
+
+    // The squares of file (0 = a) on the board.
+    pub fn file(&self, file: i8) -> u64 {
+        (0..self.ranks).fold(0, |squares, i| squares | 1 << (i * 8 + file))
+    }
+
+    // The rank side's pawns promote on.
+    pub fn promotion_rank(&self, side: Color) -> u64 {
+        let rank: u64 = (1 << self.files) - 1;
+        match side {
+            Color::White => rank << ((self.ranks - 1) * 8),
+            Color::Black => rank,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Board {
 
@@ -53,8 +108,16 @@ pub struct Board {
     pub black_queens: u64,
     pub black_king: u64,
 
+    // fairy pieces, one bitboard per entry in fairy::FAIRY_PIECES
+    pub white_fairy: [u64; FAIRY_COUNT],
+    pub black_fairy: [u64; FAIRY_COUNT],
+
     pub white_occupied: u64,
     pub black_occupied: u64,
+
+    pub duck: u64, // Duck chess: a neutral blocker, in neither white_occupied nor black_occupied
+
+    pub geometry: Geometry,
 }
 
 
@@ -75,12 +138,94 @@ impl Board { // https://doc.rust-lang.org/stable/book/ch05-03-method-syntax.html
             black_queens: BLACK_QUEEN_INIT,
             black_king: BLACK_KING_INIT,
 
+            white_fairy: [0; FAIRY_COUNT],
+            black_fairy: [0; FAIRY_COUNT],
+
             white_occupied: WHITE_OCCUPIED,
             black_occupied: BLACK_OCCUPIED,
+
+            duck: 0,
+
+            geometry: Geometry::STANDARD,
+        }
+    }
+
+    // A board without any pieces, for setting up positions.
+    pub fn empty() -> Self {
+        Self {
+            white_pawns: 0,
+            white_rooks: 0,
+            white_knights: 0,
+            white_bishops: 0,
+            white_queens: 0,
+            white_king: 0,
+
+            black_pawns: 0,
+            black_rooks: 0,
+            black_knights: 0,
+            black_bishops: 0,
+            black_queens: 0,
+            black_king: 0,
+
+            white_fairy: [0; FAIRY_COUNT],
+            black_fairy: [0; FAIRY_COUNT],
+
+            white_occupied: 0,
+            black_occupied: 0,
+
+            duck: 0,
+
+            geometry: Geometry::STANDARD,
         }
     }
 
     pub fn reset(&mut self) {
         *self = Board::new();
     }
+
+    // Returns the color and letter ('P', 'N', 'B', 'R', 'Q', 'K', or a fairy piece's letter) of the
+    // piece on square, if any.
+    pub fn piece_at(&self, square: i8) -> Option<(Color, char)> {
+
+        let mask = 1u64 << square;
+
+        let pieces = [
+            (self.white_pawns, Color::White, 'P'), (self.white_knights, Color::White, 'N'),
+            (self.white_bishops, Color::White, 'B'), (self.white_rooks, Color::White, 'R'),
+            (self.white_queens, Color::White, 'Q'), (self.white_king, Color::White, 'K'),
+            (self.black_pawns, Color::Black, 'P'), (self.black_knights, Color::Black, 'N'),
+            (self.black_bishops, Color::Black, 'B'), (self.black_rooks, Color::Black, 'R'),
+            (self.black_queens, Color::Black, 'Q'), (self.black_king, Color::Black, 'K'),
+        ];
+
+        if let Some(&(_, color, letter)) = pieces.iter().find(|(bb, _, _)| bb & mask != 0) {
+            return Some((color, letter));
+        }
+
+        for (index, piece) in FAIRY_PIECES.iter().enumerate() {
+            if self.white_fairy[index] & mask != 0 {
+                return Some((Color::White, piece.letter));
+            }
+            if self.black_fairy[index] & mask != 0 {
+                return Some((Color::Black, piece.letter));
+            }
+        }
+
+        None
+    }
+
+    // All of side's fairy pieces, whatever their type.
+    pub fn fairy_pieces(&self, side: Color) -> u64 {
+        let fairy = match side {
+            Color::White => &self.white_fairy,
+            Color::Black => &self.black_fairy,
+        };
+        fairy.iter().fold(0, |all, pieces| all | pieces)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
 }
@@ -0,0 +1,303 @@
+/*
+Bughouse: two teams of two play on two boards (A and B) at once. Team A plays white on board A and
+black on board B, team B the other colors, so partners always play opposite colors.
+
+Each board is played with Crazyhouse rules, except that a captured piece goes into the partner's
+pocket on the other board instead of the capturer's. Each board has its own clock, and the first
+board to finish decides the result for both teams.
+
+Games are exported as BPGN, where moves of both boards are listed in the order they were played,
+numbered with the board letter: "1A. e4 1B. d4 1a. e5 ...", uppercase for white, lowercase for black.
+*/
+
+use crate::clock::ChessClock;
+use crate::crazyhouse::{captured_piece, drops, update_promoted, Crazyhouse};
+use crate::game::{Game, GameError, MoveOutcome, Premove};
+use crate::outcome::Outcome;
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::Variant;
+use std::rc::Rc;
+
+pub const BOARD_A: usize = 0;
+pub const BOARD_B: usize = 1;
+
+// The rules on each board: Crazyhouse, but the session passes captured pieces to the partner.
+pub struct BughouseBoard;
+
+impl Variant for BughouseBoard {
+
+    fn name(&self) -> &'static str {
+        "Bughouse"
+    }
+
+    fn start_position(&self) -> GameState {
+        Crazyhouse.start_position()
+    }
+
+    fn extra_moves(&self, state: &GameState) -> Vec<Move> {
+        drops(state)
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, mv: &Move) {
+        update_promoted(before, after, mv);
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+        Crazyhouse.outcome(state, legal_moves)
+    }
+
+    // even with an empty pocket, the partner can pass on pieces
+    fn can_win(&self, _state: &GameState, _side: Color) -> bool {
+        true
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Team {
+    A, // white on board A, black on board B
+    B, // black on board A, white on board B
+}
+
+impl Team {
+
+    // The team of the player of `side` on `board`.
+    pub fn of(board: usize, side: Color) -> Team {
+        match (board == BOARD_A, side) {
+            (true, Color::White) | (false, Color::Black) => Team::A,
+            (true, Color::Black) | (false, Color::White) => Team::B,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BughouseOutcome {
+    pub winner: Option<Team>, // None for a draw
+    pub board: usize, // the board that decided the game
+    pub outcome: Outcome, // how that board ended
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BughouseMove {
+    pub board: usize,
+    pub side: Color,
+    pub number: u32, // full-move number on its board
+    pub san: String,
+}
+
+pub struct BughouseGame {
+    pub event: String,
+    boards: [Game; 2],
+    moves: Vec<BughouseMove>, // both boards, in the order they were played
+    outcome: Option<BughouseOutcome>,
+}
+
+impl BughouseGame {
+
+    pub fn new() -> Self {
+        Self {
+            event: String::from("?"),
+            boards: [Game::with_variant(Rc::new(BughouseBoard)), Game::with_variant(Rc::new(BughouseBoard))],
+            moves: Vec::new(),
+            outcome: None,
+        }
+    }
+
+    pub fn board(&self, board: usize) -> &Game {
+        &self.boards[board]
+    }
+
+    pub fn set_players(&mut self, board: usize, white: &str, black: &str) {
+        self.boards[board].white = white.to_string();
+        self.boards[board].black = black.to_string();
+    }
+
+    pub fn moves(&self) -> &[BughouseMove] {
+        &self.moves
+    }
+
+    pub fn outcome(&self) -> Option<BughouseOutcome> {
+        self.outcome
+    }
+
+    // Play mv on `board`, then the opponent's premove there. Captured pieces go to the partner's pocket.
+    pub fn play(&mut self, board: usize, mv: Move) -> Result<MoveOutcome, GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        let mut played = match self.play_on(board, mv) {
+            Ok(played) => played,
+            Err(error) => {
+                self.update_outcome(); // a flag may have fallen
+                return Err(error);
+            }
+        };
+
+        /* the premove is played separately so its capture is passed on too */
+        if let Some(&premove) = self.boards[board].premoves().first() {
+            let side = self.boards[board].side_to_move();
+            let number = self.boards[board].state().fullmove_number;
+            let captured = captured_piece(self.boards[board].state(), &premove);
+
+            played.premove = self.boards[board].play_premove();
+
+            if let Some(Premove::Played(premove)) = &played.premove {
+                self.pass_on(board, side, captured);
+                self.moves.push(BughouseMove { board, side, number, san: premove.san.clone() });
+            }
+        }
+
+        self.update_outcome();
+
+        Ok(played)
+    }
+
+    fn play_on(&mut self, board: usize, mv: Move) -> Result<MoveOutcome, GameError> {
+
+        let game = &self.boards[board];
+        let side = game.side_to_move();
+        let number = game.state().fullmove_number;
+        let captured = captured_piece(game.state(), &mv.normalized());
+
+        let played = self.boards[board].play_move(mv)?;
+
+        self.pass_on(board, side, captured);
+        self.moves.push(BughouseMove { board, side, number, san: played.san.clone() });
+
+        Ok(played)
+    }
+
+    // The partner plays the other color on the other board.
+    fn pass_on(&mut self, board: usize, side: Color, captured: Option<char>) {
+        if let Some(piece) = captured {
+            self.boards[1 - board].give_piece(side.opposite(), piece);
+        }
+    }
+
+    // Queue a premove for `side` on `board`, played as soon as the opponent there has moved.
+    pub fn premove(&mut self, board: usize, side: Color, mv: Move) -> Result<(), GameError> {
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        self.boards[board].premove(side, mv)
+    }
+
+    pub fn resign(&mut self, board: usize, side: Color) -> Result<BughouseOutcome, GameError> {
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        self.boards[board].resign(side)?;
+        self.update_outcome();
+        Ok(self.outcome.unwrap())
+    }
+
+    // CLOCK
+
+    // Attach a clock to each board, they start running for white.
+    pub fn set_clocks(&mut self, board_a: ChessClock, board_b: ChessClock) {
+        self.boards[BOARD_A].set_clock(board_a);
+        self.boards[BOARD_B].set_clock(board_b);
+    }
+
+    // Ends the game if a player on either board has run out of time.
+    pub fn check_time(&mut self) -> Option<BughouseOutcome> {
+
+        if self.outcome.is_some() {
+            return None;
+        }
+
+        for game in self.boards.iter_mut() {
+            game.check_time();
+        }
+        self.update_outcome();
+        self.outcome
+    }
+
+    // The first board that finished decides the game, the other board's clock stops.
+    fn update_outcome(&mut self) {
+
+        if self.outcome.is_some() {
+            return;
+        }
+
+        for board in [BOARD_A, BOARD_B] {
+
+            if let Some(outcome) = self.boards[board].outcome() {
+
+                self.outcome = Some(BughouseOutcome {
+                    winner: outcome.result.winner().map(|side| Team::of(board, side)),
+                    board,
+                    outcome,
+                });
+                self.boards[1 - board].stop_clock();
+                return;
+            }
+        }
+    }
+
+    // BPGN
+
+    // The game in BPGN. The result is from team A's point of view: "1-0" means team A won.
+    pub fn to_bpgn(&self) -> String {
+
+        let result = match self.outcome.map(|outcome| outcome.winner) {
+            Some(Some(Team::A)) => "1-0",
+            Some(Some(Team::B)) => "0-1",
+            Some(None) => "1/2-1/2",
+            None => "*",
+        };
+
+        let mut bpgn = String::new();
+
+        let tags = [
+            ("Event", self.event.as_str()),
+            ("WhiteA", self.boards[BOARD_A].white.as_str()),
+            ("BlackA", self.boards[BOARD_A].black.as_str()),
+            ("WhiteB", self.boards[BOARD_B].white.as_str()),
+            ("BlackB", self.boards[BOARD_B].black.as_str()),
+            ("Result", result),
+        ];
+
+        for (name, value) in tags {
+            bpgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        bpgn.push('\n');
+
+        let mut tokens: Vec<String> = self.moves.iter().map(|mv| {
+            let letter = match (mv.board, mv.side) {
+                (BOARD_A, Color::White) => 'A',
+                (BOARD_A, Color::Black) => 'a',
+                (_, Color::White) => 'B',
+                (_, Color::Black) => 'b',
+            };
+            format!("{}{}. {}", mv.number, letter, mv.san)
+        }).collect();
+        tokens.push(result.to_string());
+
+        // wrap lines at 80 characters like PGN
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                bpgn.push_str(&line);
+                bpgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        bpgn.push_str(&line);
+        bpgn.push('\n');
+
+        bpgn
+    }
+}
+
+impl Default for BughouseGame {
+    fn default() -> Self {
+        BughouseGame::new()
+    }
+}
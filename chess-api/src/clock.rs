@@ -0,0 +1,286 @@
+/*
+Chess clocks. A ChessClock keeps the remaining time of both players for a TimeControl, which is
+one or more stages, eg. "40 moves in 90 minutes, then 30 minutes for the rest of the game, with
+30 seconds added per move from move 1" is two stages.
+
+Each stage can add time per move in one of three ways:
+- Increment (Fischer): the increment is added after every move.
+- Bronstein delay: after the move, the time used is given back, but at most the delay.
+- Simple (US) delay: the clock doesn't start counting down until the delay has passed.
+
+The clock reads the time from a ClockSource instead of the system clock directly, so tests can
+use a ManualClock and move time forward themselves.
+*/
+
+use crate::state::Color;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub trait ClockSource {
+    // Time since some fixed starting point, must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl ClockSource for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// A clock source that only moves when told to. Clones share the same time, so a test can keep
+// one clone and give the other to the ChessClock.
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, time: Duration) {
+        *self.now.lock().unwrap() += time;
+    }
+}
+
+impl ClockSource for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Timing {
+    NoIncrement,
+    Increment(Duration),
+    Bronstein(Duration),
+    SimpleDelay(Duration),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Stage {
+    pub moves: Option<u32>, // moves to make in this stage, None (or Some(0)) for the rest of the game
+    pub time: Duration,
+    pub timing: Timing,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+
+    // No stages yet, add them with then().
+    pub fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    // A single stage for the whole game, eg. 3 minutes + 2 seconds per move.
+    pub fn sudden_death(time: Duration, timing: Timing) -> Self {
+        Self { stages: vec![Stage { moves: None, time, timing }] }
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> Self {
+        Self::sudden_death(time, Timing::Increment(increment))
+    }
+
+    // Add a stage after the existing ones, eg. 40/90+30 is
+    // TimeControl::new().then(Some(40), 90 min, Increment(30 s)).then(None, 30 min, Increment(30 s))
+    pub fn then(mut self, moves: Option<u32>, time: Duration, timing: Timing) -> Self {
+        self.stages.push(Stage { moves, time, timing });
+        self
+    }
+}
+
+
+// Both players' times and stages between turns, to roll the clock back to (takebacks).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ClockSnapshot {
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    moves_in_stage: [u32; 2],
+}
+
+pub struct ChessClock {
+    control: TimeControl,
+    source: Box<dyn ClockSource>,
+    remaining: [Duration; 2], // white, black
+    stage: [usize; 2],
+    moves_in_stage: [u32; 2],
+    running: Option<Color>,
+    turn_started: Duration,
+}
+
+fn index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+impl ChessClock {
+
+    pub fn new(mut control: TimeControl, source: Box<dyn ClockSource>) -> Self {
+
+        /* a stage of no moves would never end, it's the rest of the game */
+        for stage in &mut control.stages {
+            stage.moves = stage.moves.filter(|&moves| moves > 0);
+        }
+
+        let first = control.stages.first().map_or(Duration::ZERO, |stage| stage.time);
+
+        Self {
+            control,
+            source,
+            remaining: [first, first],
+            stage: [0, 0],
+            moves_in_stage: [0, 0],
+            running: None,
+            turn_started: Duration::ZERO,
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    // Start (or switch to) `color`'s clock.
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some(color);
+        self.turn_started = self.source.now();
+    }
+
+    // Stop the clock, charging the running player for the time used (without increment or move count).
+    pub fn stop(&mut self) {
+        if let Some(color) = self.running {
+            let charged = self.charged(color);
+            let i = index(color);
+            self.remaining[i] = self.remaining[i].saturating_sub(charged);
+            self.running = None;
+        }
+    }
+
+    pub fn running(&self) -> Option<Color> {
+        self.running
+    }
+
+    // Remaining time for color right now, including the time of a running turn.
+    pub fn remaining(&self, color: Color) -> Duration {
+        let remaining = self.remaining[index(color)];
+
+        if self.running == Some(color) {
+            return remaining.saturating_sub(self.charged(color));
+        }
+        remaining
+    }
+
+    // Moves left until the next time control, None in the last (sudden death) stage.
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        let i = index(color);
+        let stage = self.control.stages.get(self.stage[i])?;
+        stage.moves.map(|moves| moves - self.moves_in_stage[i])
+    }
+
+    // The player whose time has run out, if any.
+    pub fn flagged(&self) -> Option<Color> {
+        let color = self.running?;
+        if self.remaining(color).is_zero() {
+            return Some(color);
+        }
+        None
+    }
+
+    /*
+    `color` has made a move: charge the time used, add the increment or delay, advance the stage
+    when its moves are done, and start the opponent's clock.
+    Returns Err(color) (and stops the clock) if the time ran out before the move.
+    */
+    pub fn press(&mut self, color: Color) -> Result<(), Color> {
+
+        let i = index(color);
+        let now = self.source.now();
+        let elapsed = if self.running == Some(color) { now - self.turn_started } else { Duration::ZERO };
+        let charged = if self.running == Some(color) { self.charged(color) } else { Duration::ZERO };
+
+        if charged >= self.remaining[i] {
+            self.remaining[i] = Duration::ZERO;
+            self.running = None;
+            return Err(color);
+        }
+
+        self.remaining[i] -= charged;
+
+        if let Some(stage) = self.control.stages.get(self.stage[i]) {
+
+            match stage.timing {
+                Timing::Increment(increment) => self.remaining[i] += increment,
+                Timing::Bronstein(delay) => self.remaining[i] += elapsed.min(delay),
+                Timing::NoIncrement | Timing::SimpleDelay(_) => {}
+            }
+
+            self.moves_in_stage[i] += 1;
+
+            if stage.moves == Some(self.moves_in_stage[i]) {
+                self.stage[i] += 1;
+                self.moves_in_stage[i] = 0;
+                if let Some(next) = self.control.stages.get(self.stage[i]) {
+                    self.remaining[i] += next.time;
+                }
+            }
+        }
+
+        self.running = Some(color.opposite());
+        self.turn_started = now;
+
+        Ok(())
+    }
+
+    // The times at the start of the running turn (they only change when a turn ends).
+    pub fn snapshot(&self) -> ClockSnapshot {
+        ClockSnapshot {
+            remaining: self.remaining,
+            stage: self.stage,
+            moves_in_stage: self.moves_in_stage,
+        }
+    }
+
+    // Go back to the times of snapshot, with color's clock running from now.
+    pub fn restore(&mut self, snapshot: ClockSnapshot, color: Color) {
+        self.remaining = snapshot.remaining;
+        self.stage = snapshot.stage;
+        self.moves_in_stage = snapshot.moves_in_stage;
+        self.running = Some(color);
+        self.turn_started = self.source.now();
+    }
+
+    // Time charged for the running turn so far (a simple delay isn't charged).
+    fn charged(&self, color: Color) -> Duration {
+
+        let elapsed = self.source.now() - self.turn_started;
+
+        match self.control.stages.get(self.stage[index(color)]).map(|stage| stage.timing) {
+            Some(Timing::SimpleDelay(delay)) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+}
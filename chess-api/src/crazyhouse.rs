@@ -0,0 +1,133 @@
+/*
+Crazyhouse: a captured piece changes sides and goes into the capturer's pocket (GameState.pockets).
+Instead of moving, a player can drop a piece from their pocket onto any empty square.
+
+- Pawns can't be dropped on the 1st or 8th rank.
+- A promoted piece goes back to being a pawn when it's captured. Promoted pieces are tracked in
+  GameState.promoted, and written in FEN with a "~" (eg. "Q~").
+- Drops are ordinary legal moves, so a check can be answered by dropping a piece in between, and
+  it's only checkmate if no drop helps either.
+- Since pieces always come back into play, there's no draw by insufficient material.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{is_capture, is_check, Move};
+use crate::state::{Color, GameState, Pocket, Pockets};
+use crate::variant::Variant;
+
+const FIRST_AND_EIGHTH_RANK: u64 = 0xFF000000000000FF;
+
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    fn start_position(&self) -> GameState {
+        GameState {
+            pockets: Some(Pockets::default()),
+            ..GameState::new()
+        }
+    }
+
+    fn extra_moves(&self, state: &GameState) -> Vec<Move> {
+        drops(state)
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, mv: &Move) {
+
+        if let Some(piece) = captured_piece(before, mv) {
+            after.pockets.get_or_insert_with(Pockets::default).get_mut(before.side_to_move).add(piece);
+        }
+
+        update_promoted(before, after, mv);
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        if legal_moves.is_empty() {
+            if is_check(state, side.opposite()) {
+                return Some(Outcome::win(side.opposite(), Termination::Checkmate));
+            }
+            return Some(Outcome::draw(Termination::Stalemate));
+        }
+
+        None
+    }
+
+    // a lone king can still get pieces from its pocket
+    fn can_win(&self, _state: &GameState, _side: Color) -> bool {
+        true
+    }
+}
+
+// All drops the side to move can make from its pocket.
+pub fn drops(state: &GameState) -> Vec<Move> {
+
+    let mut moves = Vec::new();
+
+    let pocket = match state.pockets {
+        Some(pockets) => *pockets.get(state.side_to_move),
+        None => return moves,
+    };
+
+    let empty = !(state.board.white_occupied | state.board.black_occupied);
+
+    for piece in Pocket::PIECES {
+
+        if pocket.count(piece) == 0 {
+            continue;
+        }
+
+        let mut squares = empty;
+        if piece == 'P' {
+            squares &= !FIRST_AND_EIGHTH_RANK;
+        }
+
+        for square in 0..64 {
+            if squares & 1<<square != 0 {
+                moves.push(Move::new_drop(piece, square));
+            }
+        }
+    }
+
+    moves
+}
+
+// The piece mv captures, as it goes into a pocket: a promoted piece goes back to being a pawn.
+pub fn captured_piece(before: &GameState, mv: &Move) -> Option<char> {
+
+    if mv.drop.is_some() || !is_capture(mv.from, mv.to, before) {
+        return None;
+    }
+
+    let to_mask: u64 = 1<<mv.to;
+
+    match before.board.piece_at(mv.to) {
+        Some(_) if before.promoted & to_mask != 0 => Some('P'),
+        Some((_, piece)) => Some(piece),
+        None => Some('P'), // en passant
+    }
+}
+
+// Moves the promoted mark along with the piece that moved, and clears it from a captured piece.
+pub fn update_promoted(before: &GameState, after: &mut GameState, mv: &Move) {
+
+    if mv.drop.is_some() {
+        return;
+    }
+
+    let from_mask: u64 = 1<<mv.from;
+    let to_mask: u64 = 1<<mv.to;
+
+    let moved_promoted = before.promoted & from_mask != 0 || mv.promotion.is_some();
+
+    after.promoted &= !(from_mask | to_mask);
+    if moved_promoted {
+        after.promoted |= to_mask;
+    }
+}
@@ -0,0 +1,74 @@
+/*
+Duck chess: after every move the player puts the duck (Board.duck) on an empty square. The duck
+belongs to neither side: no piece can move onto or through it, and it can't be captured.
+
+- Every move has two parts (Move.duck), the duck must go to a different square than it stood on.
+  In SAN the duck's square follows a comma, eg. "Nf3,d5". In FEN the duck is a "*".
+- The duck can shield a king as well as trap it, so check is dropped: any move may leave the king
+  attacked, and the game ends when a king is taken (Termination::KingCaptured).
+- Castling keeps its usual conditions, and the duck standing between king and rook blocks it.
+- A player who can't move at all wins.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::{next_state, Variant};
+
+pub struct DuckChess;
+
+impl Variant for DuckChess {
+
+    fn name(&self) -> &'static str {
+        "Duck Chess"
+    }
+
+    fn in_check(&self, _state: &GameState, _side: Color) -> bool {
+        false
+    }
+
+    // every move comes with each square the duck can be put on afterwards
+    fn filter_moves(&self, state: &GameState, moves: Vec<Move>) -> Vec<Move> {
+
+        let mut duck_moves = Vec::new();
+
+        for mv in moves {
+
+            let after = next_state(self, state, &mv).board;
+            let empty = !(after.white_occupied | after.black_occupied | after.duck);
+
+            for square in 0..64 {
+                if empty & 1<<square != 0 {
+                    duck_moves.push(mv.with_duck(square));
+                }
+            }
+        }
+
+        duck_moves
+    }
+
+    fn after_move(&self, _before: &GameState, after: &mut GameState, mv: &Move) {
+        if let Some(square) = mv.duck {
+            after.board.duck = 1<<square;
+        }
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        let king = match side {
+            Color::White => state.board.white_king,
+            Color::Black => state.board.black_king,
+        };
+
+        if king == 0 {
+            return Some(Outcome::win(side.opposite(), Termination::KingCaptured));
+        }
+        if legal_moves.is_empty() {
+            return Some(Outcome::win(side, Termination::Stalemate));
+        }
+
+        None
+    }
+}
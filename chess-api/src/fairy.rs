@@ -0,0 +1,66 @@
+/*
+Fairy pieces on the 8x8 board, built from movement components:
+
+- Knight, Bishop, Rook: moves like that piece, using knight_moves(), bishop_moves(), rook_moves()
+- Leaper(a, b): jumps a squares along one axis and b along the other, in all directions
+  (the knight would be Leaper(1, 2))
+
+A piece moves (and attacks) like all of its components together. Each piece in FAIRY_PIECES has a
+bitboard per color in Board.white_fairy / Board.black_fairy (at the same index), and a FEN letter,
+uppercase for white. New pieces only need an entry in FAIRY_PIECES.
+
+Fairy pieces can be set up through FEN or the board, pawns don't promote to them.
+*/
+
+use crate::legal_moves::{bishop_moves, knight_moves, leaper_moves, rook_moves};
+use crate::state::{Color, GameState};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Component {
+    Knight,
+    Bishop,
+    Rook,
+    Leaper(i8, i8),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FairyPiece {
+    pub name: &'static str,
+    pub letter: char, // uppercase, in FEN and SAN
+    pub components: &'static [Component],
+}
+
+pub const FAIRY_PIECES: [FairyPiece; 6] = [
+    FairyPiece { name: "Archbishop", letter: 'A', components: &[Component::Knight, Component::Bishop] },
+    FairyPiece { name: "Chancellor", letter: 'C', components: &[Component::Knight, Component::Rook] },
+    FairyPiece { name: "Amazon", letter: 'Z', components: &[Component::Knight, Component::Bishop, Component::Rook] },
+    FairyPiece { name: "Camel", letter: 'L', components: &[Component::Leaper(1, 3)] },
+    // S-Chess names the archbishop and chancellor hawk and elephant
+    FairyPiece { name: "Hawk", letter: 'H', components: &[Component::Knight, Component::Bishop] },
+    FairyPiece { name: "Elephant", letter: 'E', components: &[Component::Knight, Component::Rook] },
+];
+
+pub const FAIRY_COUNT: usize = FAIRY_PIECES.len();
+
+// The index in FAIRY_PIECES of the piece with this letter (either case).
+pub fn fairy_index(letter: char) -> Option<usize> {
+    FAIRY_PIECES.iter().position(|piece| piece.letter == letter.to_ascii_uppercase())
+}
+
+// All squares the fairy pieces FAIRY_PIECES[index] in pos can move to, like the other *_moves().
+pub fn fairy_moves(pos: u64, index: usize, state: &GameState, side: Color) -> u64 {
+
+    let mut targeted_squares: u64 = 0;
+
+    for component in FAIRY_PIECES[index].components {
+
+        targeted_squares |= match *component {
+            Component::Knight => knight_moves(pos, state, side),
+            Component::Bishop => bishop_moves(pos, state, side),
+            Component::Rook => rook_moves(pos, state, side),
+            Component::Leaper(a, b) => leaper_moves(pos, a, b, state, side),
+        };
+    }
+
+    targeted_squares
+}
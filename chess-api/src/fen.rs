@@ -0,0 +1,493 @@
+/*
+Reading and writing positions in FEN (Forsyth-Edwards Notation), https://www.chessprogramming.org/Forsyth-Edwards_Notation
+
+A FEN string has six fields separated by spaces:
+1. piece placement, rank 8 first, uppercase = white, digits = empty squares, eg. "rnbqkbnr/pppppppp/8/..."
+2. side to move, "w" or "b"
+3. castling rights, eg. "KQkq", or "-"
+   For Chess960 the rook files are written instead when K/Q would be ambiguous (X-FEN, eg. "KGkg"),
+   or always (Shredder-FEN, eg. "HAha"). Both are accepted when reading.
+4. en passant square, eg. "e3", or "-"
+5. half-move clock
+6. full-move number
+
+The last two fields are optional when reading (defaulting to 0 and 1).
+
+Three-check adds the checks given by white and black as a last field, eg. "... 0 1 +2+0".
+Crazyhouse adds the pieces in hand after the placement, eg. "...RNBQKBNR[Qnp]", and marks promoted
+pieces with a "~" after their letter, eg. "Q~".
+S-Chess writes the reserve hawk and elephant like pieces in hand ("[HEhe]"), and adds the files that
+can still gate to the castling rights, eg. "KQBCDFGkqbcdfg" (K and Q also stand for the king's and
+rooks' files). While a side has a reserve piece, its file letters always mean gating, not X-FEN.
+Duck chess writes the duck as "*" in the placement, fairy pieces use their letter from
+fairy::FAIRY_PIECES (eg. "A" for a white archbishop).
+Smaller boards (minichess) have fewer ranks and files in the placement, eg. "rnbqk/ppppp/5/PPPPP/RNBQK".
+*/
+
+use crate::bitboards::{Board, Geometry};
+use crate::fairy::fairy_index;
+use crate::notation::{parse_square, square_name};
+use crate::state::{CastlingFiles, CheckCounter, Color, GameState, Pocket, Pockets};
+
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FenError {
+    MissingField,
+    InvalidPlacement,
+    InvalidSideToMove,
+    InvalidCastling,
+    InvalidEnPassant,
+    InvalidClock,
+    InvalidCheckCount,
+    InvalidPocket,
+}
+
+pub fn to_fen(state: &GameState) -> String {
+    fen_string(state, false)
+}
+
+// Like to_fen(), but Chess960 castling rights are always written as rook files ("HAha").
+pub fn to_shredder_fen(state: &GameState) -> String {
+    fen_string(state, true)
+}
+
+fn fen_string(state: &GameState, shredder: bool) -> String {
+
+    let mut placement = String::new();
+    let geometry = state.board.geometry;
+
+    for rank in (0..geometry.ranks).rev() {
+
+        let mut empty = 0;
+
+        for file in 0..geometry.files {
+            match state.board.piece_at(rank * 8 + file) {
+                Some((color, letter)) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(match color {
+                        Color::White => letter,
+                        Color::Black => letter.to_ascii_lowercase(),
+                    });
+                    if state.promoted & 1<<(rank * 8 + file) != 0 {
+                        placement.push('~');
+                    }
+                }
+                None if state.board.duck & 1<<(rank * 8 + file) != 0 => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push('*'); // the duck
+                }
+                None => empty += 1,
+            }
+        }
+
+        if empty > 0 {
+            placement.push_str(&empty.to_string());
+        }
+        if rank > 0 {
+            placement.push('/');
+        }
+    }
+
+    if let Some(pockets) = state.pockets {
+        placement.push('[');
+        for piece in Pocket::GATING_PIECES.iter().chain(Pocket::PIECES.iter()) {
+            placement.push_str(&piece.to_string().repeat(pockets.white.count(*piece) as usize));
+        }
+        for piece in Pocket::GATING_PIECES.iter().chain(Pocket::PIECES.iter()) {
+            placement.push_str(&piece.to_ascii_lowercase().to_string().repeat(pockets.black.count(*piece) as usize));
+        }
+        placement.push(']');
+    }
+
+    let side = match state.side_to_move {
+        Color::White => "w",
+        Color::Black => "b",
+    };
+
+    let castling = castling_field(state, state.chess960 && shredder);
+
+    let en_passant = if state.en_passant_mask != 0 {
+        square_name(state.en_passant_mask.trailing_zeros() as i8)
+    } else {
+        "-".to_string()
+    };
+
+    let mut fen = format!("{} {} {} {} {} {}", placement, side, castling, en_passant, state.halfmove_clock, state.fullmove_number);
+
+    if let Some(checks) = state.checks {
+        fen.push_str(&format!(" +{}+{}", checks.white, checks.black));
+    }
+
+    fen
+}
+
+pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+
+    let mut state = GameState::new();
+
+    // Three-check counters
+    if let Some(counter) = fields.last().and_then(|field| field.strip_prefix('+')) {
+        state.checks = Some(parse_checks(counter)?);
+        fields.pop();
+    }
+
+    if fields.len() < 4 {
+        return Err(FenError::MissingField);
+    }
+
+    let placement = match fields[0].split_once('[') {
+        Some((placement, pockets)) => {
+            let pockets = pockets.strip_suffix(']').ok_or(FenError::InvalidPocket)?;
+            state.pockets = Some(parse_pockets(pockets)?);
+            placement
+        }
+        None => fields[0],
+    };
+    (state.board, state.promoted) = parse_placement(placement)?;
+
+    state.side_to_move = match fields[1] {
+        "w" => Color::White,
+        "b" => Color::Black,
+        _ => return Err(FenError::InvalidSideToMove),
+    };
+
+    parse_castling(fields[2], &mut state)?;
+
+    state.en_passant_mask = match fields[3] {
+        "-" => 0,
+        square => 1 << parse_square(square).ok_or(FenError::InvalidEnPassant)?,
+    };
+
+    state.halfmove_clock = match fields.get(4) {
+        Some(clock) => clock.parse().map_err(|_| FenError::InvalidClock)?,
+        None => 0,
+    };
+
+    state.fullmove_number = match fields.get(5) {
+        Some(number) => number.parse().map_err(|_| FenError::InvalidClock)?,
+        None => 1,
+    };
+
+    Ok(state)
+}
+
+// "N+M" (the first "+" already removed): checks given by white and black.
+fn parse_checks(counter: &str) -> Result<CheckCounter, FenError> {
+
+    let (white, black) = counter.split_once('+').ok_or(FenError::InvalidCheckCount)?;
+
+    Ok(CheckCounter {
+        white: white.parse().map_err(|_| FenError::InvalidCheckCount)?,
+        black: black.parse().map_err(|_| FenError::InvalidCheckCount)?,
+    })
+}
+
+// Pieces in hand, eg. "Qnp" (uppercase = white).
+fn parse_pockets(text: &str) -> Result<Pockets, FenError> {
+
+    let mut pockets = Pockets::default();
+
+    for c in text.chars() {
+
+        let piece = c.to_ascii_uppercase();
+        if !Pocket::PIECES.contains(&piece) && !Pocket::GATING_PIECES.contains(&piece) {
+            return Err(FenError::InvalidPocket);
+        }
+
+        let side = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        pockets.get_mut(side).add(c);
+    }
+
+    Ok(pockets)
+}
+
+// The board, and the squares of promoted pieces. Fewer than 8 ranks and files make a smaller board
+// (Board.geometry), every rank needs the same number of files. 8 files with fewer ranks (or the other
+// way around) is most likely a truncated FEN, not a board size.
+fn parse_placement(placement: &str) -> Result<(Board, u64), FenError> {
+
+    let mut board = Board::empty();
+    let mut promoted: u64 = 0;
+
+    let ranks: Vec<&str> = placement.split('/').collect();
+
+    if ranks.len() > 8 {
+        return Err(FenError::InvalidPlacement);
+    }
+
+    let mut files: Option<i8> = None;
+
+    for (i, rank_text) in ranks.iter().enumerate() {
+
+        let rank = ranks.len() as i8 - 1 - i as i8; // the last rank comes first
+        let mut file: i8 = 0;
+        let mut previous: Option<char> = None;
+
+        for c in rank_text.chars() {
+
+            let after = previous.replace(c);
+
+            // a run of 1 to 8 empty squares, written as a single digit
+            if let Some(empty) = c.to_digit(10) {
+                if empty == 0 || after.is_some_and(|c| c.is_ascii_digit()) || file + empty as i8 > 8 {
+                    return Err(FenError::InvalidPlacement);
+                }
+                file += empty as i8;
+                continue;
+            }
+
+            // the piece before was promoted (not an empty square, the duck or another "~")
+            if c == '~' {
+                if !after.is_some_and(|c| c.is_ascii_alphabetic()) {
+                    return Err(FenError::InvalidPlacement);
+                }
+                promoted |= 1 << (rank * 8 + file - 1);
+                continue;
+            }
+
+            if file > 7 {
+                return Err(FenError::InvalidPlacement);
+            }
+
+            let mask: u64 = 1 << (rank * 8 + file);
+
+            // the duck (Duck chess)
+            if c == '*' {
+                board.duck |= mask;
+                file += 1;
+                continue;
+            }
+
+            let bitboard = match c {
+                'P' => &mut board.white_pawns,
+                'N' => &mut board.white_knights,
+                'B' => &mut board.white_bishops,
+                'R' => &mut board.white_rooks,
+                'Q' => &mut board.white_queens,
+                'K' => &mut board.white_king,
+                'p' => &mut board.black_pawns,
+                'n' => &mut board.black_knights,
+                'b' => &mut board.black_bishops,
+                'r' => &mut board.black_rooks,
+                'q' => &mut board.black_queens,
+                'k' => &mut board.black_king,
+                _ => match (fairy_index(c), c.is_ascii_uppercase()) {
+                    (Some(index), true) => &mut board.white_fairy[index],
+                    (Some(index), false) => &mut board.black_fairy[index],
+                    (None, _) => return Err(FenError::InvalidPlacement),
+                },
+            };
+            *bitboard |= mask;
+
+            if c.is_ascii_uppercase() {
+                board.white_occupied |= mask;
+            } else {
+                board.black_occupied |= mask;
+            }
+
+            file += 1;
+        }
+
+        if file == 0 || file > 8 || files.is_some_and(|files| files != file) {
+            return Err(FenError::InvalidPlacement);
+        }
+        files = Some(file);
+    }
+
+    let files = files.unwrap();
+    if (files == 8) != (ranks.len() == 8) {
+        return Err(FenError::InvalidPlacement);
+    }
+
+    board.geometry = Geometry::new(files, ranks.len() as i8);
+
+    Ok((board, promoted))
+}
+
+fn castling_field(state: &GameState, shredder: bool) -> String {
+
+    let mut castling = String::new();
+
+    for side in [Color::White, Color::Black] {
+
+        let rights = match side {
+            Color::White => [(true, state.white_can_castle_kingside), (false, state.white_can_castle_queenside)],
+            Color::Black => [(true, state.black_can_castle_kingside), (false, state.black_can_castle_queenside)],
+        };
+
+        let mut letters = Vec::new();
+
+        for (kingside, right) in rights {
+
+            if !right {
+                continue;
+            }
+
+            let files = state.castling_files(side);
+            let rook_file = if kingside { files.kingside_rook } else { files.queenside_rook };
+
+            // K/Q means the outermost rook on that side of the king
+            let letter = if !shredder && outermost_rook(state, side, files.king, kingside) == Some(rook_file) {
+                if kingside { 'K' } else { 'Q' }
+            } else {
+                (b'A' + rook_file as u8) as char
+            };
+            letters.push(letter);
+        }
+
+        // S-Chess: the files that can still gate, unless the castling rights already say so
+        if has_gating_reserve(state, side) {
+            let gating = (state.gating & !implied_gating(state, side)) >> back_rank_offset(state, side) & 0xFF;
+            letters.extend((0..8).filter(|file| gating & 1<<file != 0).map(|file| (b'A' + file as u8) as char));
+        }
+
+        castling.extend(letters.iter().map(|&letter| match side {
+            Color::White => letter,
+            Color::Black => letter.to_ascii_lowercase(),
+        }));
+    }
+
+    if castling.is_empty() {
+        castling.push('-');
+    }
+
+    castling
+}
+
+fn parse_castling(field: &str, state: &mut GameState) -> Result<(), FenError> {
+
+    state.white_can_castle_kingside = false;
+    state.white_can_castle_queenside = false;
+    state.black_can_castle_kingside = false;
+    state.black_can_castle_queenside = false;
+
+    if field == "-" {
+        return Ok(());
+    }
+
+    state.gating = 0;
+
+    for c in field.chars() {
+
+        let side = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+
+        // S-Chess: with a hawk or elephant in reserve, a file is a square that can still gate
+        if let letter @ 'A'..='H' = c.to_ascii_uppercase() && has_gating_reserve(state, side) {
+            state.gating |= 1 << (back_rank_offset(state, side) + (letter as u8 - b'A') as i8);
+            continue;
+        }
+
+        let king = match side {
+            Color::White => state.board.white_king,
+            Color::Black => state.board.black_king,
+        };
+        let back_rank = king >> back_rank_offset(state, side) & 0xFF;
+
+        if back_rank == 0 {
+            return Err(FenError::InvalidCastling); // the king isn't on its back rank
+        }
+        let king_file = back_rank.trailing_zeros() as i8;
+
+        let (kingside, rook_file) = match c.to_ascii_uppercase() {
+            'K' => (true, outermost_rook(state, side, king_file, true)),
+            'Q' => (false, outermost_rook(state, side, king_file, false)),
+            letter @ 'A'..='H' => {
+                let file = (letter as u8 - b'A') as i8;
+                (file > king_file, Some(file))
+            }
+            _ => return Err(FenError::InvalidCastling),
+        };
+
+        let rook_file = rook_file.ok_or(FenError::InvalidCastling)?;
+
+        let files = match side {
+            Color::White => &mut state.white_castling_files,
+            Color::Black => &mut state.black_castling_files,
+        };
+        files.king = king_file;
+
+        match (side, kingside) {
+            (Color::White, true) => state.white_can_castle_kingside = true,
+            (Color::White, false) => state.white_can_castle_queenside = true,
+            (Color::Black, true) => state.black_can_castle_kingside = true,
+            (Color::Black, false) => state.black_can_castle_queenside = true,
+        }
+
+        if kingside {
+            files.kingside_rook = rook_file;
+        } else {
+            files.queenside_rook = rook_file;
+        }
+    }
+
+    // S-Chess: castling rights also mean the king's and those rooks' squares can gate
+    for side in [Color::White, Color::Black] {
+        if has_gating_reserve(state, side) {
+            state.gating |= implied_gating(state, side);
+        }
+    }
+
+    // any castling setup other than e/a/h is Chess960
+    state.chess960 = state.white_castling_files != CastlingFiles::STANDARD
+        || state.black_castling_files != CastlingFiles::STANDARD;
+
+    Ok(())
+}
+
+// The first square of side's back rank.
+fn back_rank_offset(state: &GameState, side: Color) -> i8 {
+    match side {
+        Color::White => 0,
+        Color::Black => (state.board.geometry.ranks - 1) * 8,
+    }
+}
+
+// Whether side still has a hawk or elephant to gate (S-Chess).
+fn has_gating_reserve(state: &GameState, side: Color) -> bool {
+    state.pockets.is_some_and(|pockets| pockets.get(side).has_gating_pieces())
+}
+
+// The squares of the king and rooks that side can still castle with.
+fn implied_gating(state: &GameState, side: Color) -> u64 {
+
+    let files = state.castling_files(side);
+    let offset = back_rank_offset(state, side);
+
+    let (kingside, queenside) = match side {
+        Color::White => (state.white_can_castle_kingside, state.white_can_castle_queenside),
+        Color::Black => (state.black_can_castle_kingside, state.black_can_castle_queenside),
+    };
+
+    let mut squares: u64 = 0;
+    if kingside {
+        squares |= 1<<(offset + files.king) | 1<<(offset + files.kingside_rook);
+    }
+    if queenside {
+        squares |= 1<<(offset + files.king) | 1<<(offset + files.queenside_rook);
+    }
+
+    squares
+}
+
+fn outermost_rook(state: &GameState, side: Color, king_file: i8, kingside: bool) -> Option<i8> {
+
+    let rooks = match side {
+        Color::White => state.board.white_rooks,
+        Color::Black => state.board.black_rooks,
+    };
+    let back_rank = rooks >> back_rank_offset(state, side) & 0xFF;
+
+    let mut files: Vec<i8> = if kingside { (king_file + 1..8).rev().collect() } else { (0..king_file).collect() };
+    files.retain(|&file| back_rank & 1<<file != 0);
+
+    files.first().copied()
+}
@@ -0,0 +1,146 @@
+/*
+Fog of war (dark chess): a player only sees their own pieces and the squares those pieces could
+move to. visible_squares() computes that mask and view() hides everything else of the opponent's.
+
+- A player can't see every attack on their king, so nobody is told about check and no move is
+  refused for leaving the king attacked. Taking the king ends the game (Termination::KingCaptured).
+- Only castling still looks at attacks the player may not see: it's refused out of or through an
+  attacked square, as in standard chess.
+- There's no draw by insufficient material, a bare king can still blunder into capture.
+*/
+
+use crate::bitboards::Board;
+use crate::fairy::{fairy_moves, FAIRY_COUNT};
+use crate::legal_moves::{bishop_moves, king_moves, knight_moves, pawn_attacks, pawn_moves, queen_moves, rook_moves};
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::Variant;
+
+pub struct FogOfWar;
+
+impl Variant for FogOfWar {
+
+    fn name(&self) -> &'static str {
+        "Fog of War"
+    }
+
+    fn in_check(&self, _state: &GameState, _side: Color) -> bool {
+        false
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        let king = match side {
+            Color::White => state.board.white_king,
+            Color::Black => state.board.black_king,
+        };
+
+        if king == 0 {
+            return Some(Outcome::win(side.opposite(), Termination::KingCaptured));
+        }
+        if legal_moves.is_empty() {
+            return Some(Outcome::draw(Termination::Stalemate));
+        }
+
+        None
+    }
+}
+
+// The squares side can see: its own pieces, every square they could move to (including
+// captures), and the diagonals in front of its pawns.
+pub fn visible_squares(state: &GameState, side: Color) -> u64 {
+
+    let board = &state.board;
+    let mut visible: u64 = 0;
+
+    match side {
+
+        Color::White => {
+
+            visible |= board.white_occupied;
+            visible |= pawn_moves(board.white_pawns, state, side) | pawn_attacks(board.white_pawns, side, &board.geometry);
+            visible |= knight_moves(board.white_knights, state, side);
+            visible |= bishop_moves(board.white_bishops, state, side);
+            visible |= rook_moves(board.white_rooks, state, side);
+            visible |= queen_moves(board.white_queens, state, side);
+            visible |= king_moves(board.white_king, state, side, false);
+            for index in 0..FAIRY_COUNT {
+                visible |= fairy_moves(board.white_fairy[index], index, state, side);
+            }
+        }
+
+        Color::Black => {
+
+            visible |= board.black_occupied;
+            visible |= pawn_moves(board.black_pawns, state, side) | pawn_attacks(board.black_pawns, side, &board.geometry);
+            visible |= knight_moves(board.black_knights, state, side);
+            visible |= bishop_moves(board.black_bishops, state, side);
+            visible |= rook_moves(board.black_rooks, state, side);
+            visible |= queen_moves(board.black_queens, state, side);
+            visible |= king_moves(board.black_king, state, side, false);
+            for index in 0..FAIRY_COUNT {
+                visible |= fairy_moves(board.black_fairy[index], index, state, side);
+            }
+        }
+    }
+
+    visible
+}
+
+// The position as side sees it: the opponent's pieces on squares side can't see are removed, and
+// so are the opponent's castling rights and an en passant square side can't see.
+pub fn view(state: &GameState, side: Color) -> GameState {
+
+    let visible = visible_squares(state, side);
+    let mut view = state.clone();
+
+    match side {
+
+        Color::White => {
+            hide_black_pieces(!visible, &mut view.board);
+            view.black_can_castle_kingside = false;
+            view.black_can_castle_queenside = false;
+        }
+
+        Color::Black => {
+            hide_white_pieces(!visible, &mut view.board);
+            view.white_can_castle_kingside = false;
+            view.white_can_castle_queenside = false;
+        }
+    }
+
+    if view.en_passant_mask & visible == 0 {
+        view.en_passant_mask = 0;
+    }
+
+    view
+}
+
+pub(crate) fn hide_white_pieces(hidden: u64, board: &mut Board) {
+    board.white_pawns &= !hidden;
+    board.white_knights &= !hidden;
+    board.white_bishops &= !hidden;
+    board.white_rooks &= !hidden;
+    board.white_queens &= !hidden;
+    board.white_king &= !hidden;
+    for fairy in board.white_fairy.iter_mut() {
+        *fairy &= !hidden;
+    }
+    board.white_occupied &= !hidden;
+}
+
+pub(crate) fn hide_black_pieces(hidden: u64, board: &mut Board) {
+    board.black_pawns &= !hidden;
+    board.black_knights &= !hidden;
+    board.black_bishops &= !hidden;
+    board.black_rooks &= !hidden;
+    board.black_queens &= !hidden;
+    board.black_king &= !hidden;
+    for fairy in board.black_fairy.iter_mut() {
+        *fairy &= !hidden;
+    }
+    board.black_occupied &= !hidden;
+}
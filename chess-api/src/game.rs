@@ -0,0 +1,799 @@
+/*
+Game is a whole game session: it owns the GameState and History, keeps the game's metadata
+(players, event, start position) and decides when and how the game ends.
+The rules come from a Variant, standard chess unless the game was created with_variant().
+
+Unlike make_move(), which returns a bool, every action on a Game returns a Result with a
+GameError explaining why it was refused.
+*/
+
+use crate::adjudication::Adjudicator;
+use crate::clock::{ChessClock, ClockSnapshot};
+use crate::fen::{from_fen, to_fen, FenError, START_FEN};
+use crate::game_tree::GameTree;
+use crate::notation::to_variant_san;
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{generate_pseudo_legal_moves, read_cur_square, Move};
+use crate::pgn::write_pgn;
+use crate::state::{Color, GameState, History, Pockets, RepetitionKey};
+use crate::variant::{self, Standard, Variant};
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    IllegalMove(Move),
+    GameOver,
+    NothingToUndo,
+    NoDrawOffer,
+    DrawNotClaimable(DrawClaim),
+    InvalidFen(FenError),
+    NoPiece(i8), // touching or adjusting an empty square (or, adjusting, an opponent's piece)
+    TouchedPiece(i8), // touch-move: the move doesn't move (or capture) the piece touched on this square
+    OwnTurn, // premoving while it's the player's own turn
+    NoTakebackRequest,
+    TakebackNotAllowed(usize), // no plies, more than the maximum, or more than were played
+}
+
+// Draws that a player may claim, but that don't end the game by themselves.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DrawClaim {
+    ThreefoldRepetition,
+    FiftyMoveRule,
+}
+
+// Touch-move: the pieces the side to move touched that it now has to move or capture.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Touched {
+    pub piece: Option<i8>, // own piece, has to be moved
+    pub target: Option<i8>, // opponent's piece, has to be captured
+    pub adjusted: u64, // own pieces announced with adjust(), touching them commits to nothing
+}
+
+// A player asks to take back their last `plies` half-moves (eg. 2: their own move and the reply).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TakebackRequest {
+    pub by: Color,
+    pub plies: usize,
+}
+
+// An answered takeback request, kept for the game record.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Takeback {
+    pub by: Color,
+    pub ply: usize, // moves played when it was requested
+    pub moves: Vec<String>, // SAN of the moves to take back
+    pub accepted: bool,
+}
+
+// What happened when a move was played.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MoveOutcome {
+    pub mv: Move,
+    pub san: String,
+    pub check: bool,
+    pub game_over: Option<Outcome>,
+    pub premove: Option<Premove>, // the opponent's queued premove, tried right after this move
+}
+
+// What became of the first queued premove once the opponent moved.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Premove {
+    Played(Box<MoveOutcome>),
+    Cancelled(Move), // not legal in the new position, the rest of the queue was dropped too
+}
+
+pub struct Game {
+    pub white: String,
+    pub black: String,
+    pub event: String,
+    start_fen: String,
+    state: GameState,
+    history: History,
+    moves: Vec<Move>,
+    positions: Vec<RepetitionKey>, // position before every move, and the current one last
+    outcome: Option<Outcome>,
+    draw_offer: Option<Color>, // the side that offered a draw
+    clock: Option<ChessClock>,
+    touch_move: bool,
+    touched: Touched,
+    premoves: Vec<Move>, // queued by the side that isn't to move
+    clock_snapshots: Vec<Option<ClockSnapshot>>, // the clock at the start of every move's turn
+    takeback_request: Option<TakebackRequest>,
+    takebacks: Vec<Takeback>,
+    max_takeback: usize,
+    variant: Rc<dyn Variant>,
+}
+
+impl Game {
+
+    pub fn new() -> Self {
+        Self::from_state(Rc::new(Standard), GameState::new(), START_FEN.to_string())
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, GameError> {
+        Self::with_variant_from_fen(Rc::new(Standard), fen)
+    }
+
+    // Chess960 game from start position number `index` (0-959).
+    pub fn new_chess960(index: u16) -> Option<Self> {
+        let state = GameState::new_chess960(index)?;
+        let fen = to_fen(&state);
+        Some(Self::from_state(Rc::new(Standard), state, fen))
+    }
+
+    // Standard chess from any position, eg. an odds game (GameState::with_odds()).
+    pub fn from_position(state: GameState) -> Self {
+        let fen = to_fen(&state);
+        Self::from_state(Rc::new(Standard), state, fen)
+    }
+
+    // A game of `variant` from its start position.
+    pub fn with_variant(variant: Rc<dyn Variant>) -> Self {
+        let state = variant.start_position();
+        let fen = to_fen(&state);
+        Self::from_state(variant, state, fen)
+    }
+
+    pub fn with_variant_from_fen(variant: Rc<dyn Variant>, fen: &str) -> Result<Self, GameError> {
+        let state = from_fen(fen).map_err(GameError::InvalidFen)?;
+        /* normalized, so that a start position written differently is still recognized */
+        let fen = to_fen(&state);
+        Ok(Self::from_state(variant, state, fen))
+    }
+
+    fn from_state(variant: Rc<dyn Variant>, state: GameState, start_fen: String) -> Self {
+        let mut game = Self {
+            white: String::from("?"),
+            black: String::from("?"),
+            event: String::from("?"),
+            start_fen,
+            positions: vec![state.repetition_key()],
+            state,
+            history: History::new(),
+            moves: Vec::new(),
+            outcome: None,
+            draw_offer: None,
+            clock: None,
+            touch_move: false,
+            touched: Touched::default(),
+            premoves: Vec::new(),
+            clock_snapshots: Vec::new(),
+            takeback_request: None,
+            takebacks: Vec::new(),
+            max_takeback: 2,
+            variant,
+        };
+        game.outcome = game.board_outcome();
+        game
+    }
+
+    pub fn variant(&self) -> &dyn Variant {
+        self.variant.as_ref()
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn start_fen(&self) -> &str {
+        &self.start_fen
+    }
+
+    pub fn fen(&self) -> String {
+        to_fen(&self.state)
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.state.side_to_move
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.outcome.is_some() {
+            return Vec::new();
+        }
+        variant::legal_moves(self.variant.as_ref(), &self.state)
+    }
+
+    // The result, or None while the game is still going.
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+
+    // Plays mv, then the opponent's first premove if there is one.
+    pub fn play(&mut self, mv: Move) -> Result<MoveOutcome, GameError> {
+        let mut played = self.play_move(mv)?;
+        played.premove = self.play_premove();
+        Ok(played)
+    }
+
+    pub(crate) fn play_move(&mut self, mv: Move) -> Result<MoveOutcome, GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        let mv = mv.normalized();
+
+        if self.check_time().is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        if !self.legal_moves().contains(&mv) {
+            return Err(GameError::IllegalMove(mv));
+        }
+        if let Some(square) = self.touch_violation(&mv) {
+            return Err(GameError::TouchedPiece(square));
+        }
+
+        let side = self.state.side_to_move;
+        let san = to_variant_san(self.variant.as_ref(), &mv, &self.state);
+
+        // moving instead of answering declines a takeback request
+        if self.takeback_request.is_some() {
+            self.answer_takeback(false);
+        }
+
+        let snapshot = self.clock.as_ref().map(|clock| clock.snapshot());
+
+        if let Some(clock) = &mut self.clock && let Err(flagged) = clock.press(side) {
+            let outcome = self.timeout_outcome(flagged);
+            self.finish(outcome)?;
+            return Err(GameError::GameOver);
+        }
+
+        variant::make_move(self.variant.as_ref(), &mv, &mut self.state, &mut self.history);
+
+        self.moves.push(mv);
+        self.positions.push(self.state.repetition_key());
+        self.clock_snapshots.push(snapshot);
+        self.touched = Touched::default();
+
+        // moving instead of answering declines the opponent's draw offer
+        if self.draw_offer == Some(side.opposite()) {
+            self.draw_offer = None;
+        }
+
+        self.outcome = self.board_outcome();
+
+        if self.outcome.is_some() && let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+
+        Ok(MoveOutcome {
+            mv,
+            san,
+            check: self.variant.in_check(&self.state, side.opposite()),
+            game_over: self.outcome,
+            premove: None,
+        })
+    }
+
+    // Take back the last move, the clock goes back to the times at the start of that turn. Not
+    // possible once a player resigned, agreed to or claimed a draw.
+    pub fn undo(&mut self) -> Result<Move, GameError> {
+
+        if let Some(outcome) = self.outcome && !outcome.termination.is_on_board() {
+            return Err(GameError::GameOver);
+        }
+
+        let previous = self.history.pop().ok_or(GameError::NothingToUndo)?;
+        self.state = previous;
+        self.positions.pop();
+        self.outcome = None;
+        self.draw_offer = None;
+        self.touched = Touched::default();
+        self.premoves.clear();
+        self.takeback_request = None;
+
+        if let (Some(clock), Some(Some(snapshot))) = (&mut self.clock, self.clock_snapshots.pop()) {
+            clock.restore(snapshot, self.state.side_to_move);
+        }
+
+        Ok(self.moves.pop().unwrap())
+    }
+
+    pub fn resign(&mut self, color: Color) -> Result<Outcome, GameError> {
+        self.finish(Outcome::win(color.opposite(), Termination::Resignation))
+    }
+
+    pub fn offer_draw(&mut self, color: Color) -> Result<(), GameError> {
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        self.draw_offer = Some(color);
+        Ok(())
+    }
+
+    pub fn draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+
+    // `color` accepts the draw the opponent offered.
+    pub fn accept_draw(&mut self, color: Color) -> Result<Outcome, GameError> {
+        if self.draw_offer != Some(color.opposite()) {
+            return Err(GameError::NoDrawOffer);
+        }
+        self.finish(Outcome::draw(Termination::DrawAgreement))
+    }
+
+    pub fn decline_draw(&mut self, color: Color) -> Result<(), GameError> {
+        if self.draw_offer != Some(color.opposite()) {
+            return Err(GameError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    // ADJUDICATION
+
+    // Ends the game if adjudicator's rules decide it in the current position.
+    pub fn adjudicate(&mut self, adjudicator: &Adjudicator) -> Option<Outcome> {
+
+        if self.outcome.is_some() {
+            return None;
+        }
+
+        let outcome = adjudicator.verdict(&self.state, self.variant.as_ref())?;
+        self.finish(outcome).ok()
+    }
+
+    // TAKEBACKS
+
+    // The most plies a player may ask to take back (2 by default).
+    pub fn set_max_takeback(&mut self, plies: usize) {
+        self.max_takeback = plies;
+    }
+
+    // `color` asks the opponent to take back the last `plies` moves.
+    pub fn request_takeback(&mut self, color: Color, plies: usize) -> Result<(), GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if plies == 0 || plies > self.max_takeback || plies > self.moves.len() {
+            return Err(GameError::TakebackNotAllowed(plies));
+        }
+
+        self.takeback_request = Some(TakebackRequest { by: color, plies });
+        Ok(())
+    }
+
+    pub fn takeback_request(&self) -> Option<TakebackRequest> {
+        self.takeback_request
+    }
+
+    // Every answered takeback request so far.
+    pub fn takebacks(&self) -> &[Takeback] {
+        &self.takebacks
+    }
+
+    // `color` accepts the opponent's request: the moves are undone and the clock goes back to the
+    // times at the start of the turn that's played again.
+    pub fn accept_takeback(&mut self, color: Color) -> Result<Vec<Move>, GameError> {
+
+        let request = match self.takeback_request {
+            Some(request) if request.by != color => request,
+            _ => return Err(GameError::NoTakebackRequest),
+        };
+
+        self.answer_takeback(true);
+
+        /* each undo restores the clock, the last one to the start of the turn played again */
+        let mut undone = Vec::new();
+        for _ in 0..request.plies {
+            undone.push(self.undo()?);
+        }
+        undone.reverse();
+
+        Ok(undone)
+    }
+
+    pub fn decline_takeback(&mut self, color: Color) -> Result<(), GameError> {
+        match self.takeback_request {
+            Some(request) if request.by != color => {
+                self.answer_takeback(false);
+                Ok(())
+            }
+            _ => Err(GameError::NoTakebackRequest),
+        }
+    }
+
+    // Records the pending request as accepted or declined.
+    fn answer_takeback(&mut self, accepted: bool) {
+
+        let request = match self.takeback_request.take() {
+            Some(request) => request,
+            None => return,
+        };
+
+        let ply = self.moves.len();
+        let first = ply - request.plies;
+        let moves = (first..ply)
+            .map(|i| to_variant_san(self.variant.as_ref(), &self.moves[i], self.history.get_ref(i)))
+            .collect();
+
+        self.takebacks.push(Takeback { by: request.by, ply, moves, accepted });
+    }
+
+    // The draws that can be claimed in the current position.
+    pub fn draw_claims(&self) -> Vec<DrawClaim> {
+
+        let mut claims = Vec::new();
+
+        if self.outcome.is_some() {
+            return claims;
+        }
+
+        if self.repetitions() >= 3 {
+            claims.push(DrawClaim::ThreefoldRepetition);
+        }
+        if self.state.halfmove_clock >= 100 {
+            claims.push(DrawClaim::FiftyMoveRule);
+        }
+
+        claims
+    }
+
+    pub fn claim_draw(&mut self, claim: DrawClaim) -> Result<Outcome, GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if !self.draw_claims().contains(&claim) {
+            return Err(GameError::DrawNotClaimable(claim));
+        }
+
+        let termination = match claim {
+            DrawClaim::ThreefoldRepetition => Termination::ThreefoldRepetition,
+            DrawClaim::FiftyMoveRule => Termination::FiftyMoveRule,
+        };
+        self.finish(Outcome::draw(termination))
+    }
+
+    // The game as PGN, with the players, event and (if it's not the standard start) start position.
+    pub fn to_pgn(&self) -> String {
+
+        let mut start = from_fen(&self.start_fen).unwrap_or_default();
+        start.chess960 = self.state.chess960;
+        let mut tree = GameTree::with_variant(self.variant.clone(), start);
+
+        tree.set_tag("Event", &self.event);
+        tree.set_tag("White", &self.white);
+        tree.set_tag("Black", &self.black);
+        tree.set_tag("Result", self.outcome.map_or("*", |outcome| outcome.result.as_pgn()));
+        if let Some(outcome) = self.outcome {
+            tree.set_tag("Termination", outcome.termination.as_pgn());
+        }
+
+        if self.state.chess960 {
+            tree.set_tag("Variant", "Chess960");
+        } else if self.variant.name() != Standard.name() {
+            tree.set_tag("Variant", self.variant.name());
+        }
+        if self.start_fen != to_fen(&self.variant.start_position()) {
+            tree.set_tag("SetUp", "1");
+            tree.set_tag("FEN", &self.start_fen);
+        }
+
+        for ply in 0..=self.moves.len() {
+            if let Some(comment) = self.takeback_comment(ply) {
+                tree.set_comment(tree.cursor(), &comment);
+            }
+            if let Some(&mv) = self.moves.get(ply) {
+                tree.play(mv);
+            }
+        }
+
+        write_pgn(&tree)
+    }
+
+    // The takebacks after move `ply`, for the PGN: an accepted one where the game went on from,
+    // eg. "Takeback by White: Nf3 d5 (accepted)".
+    fn takeback_comment(&self, ply: usize) -> Option<String> {
+
+        let comments: Vec<String> = self.takebacks.iter()
+            .filter(|takeback| match takeback.accepted {
+                true => takeback.ply - takeback.moves.len() == ply,
+                false => takeback.ply == ply,
+            })
+            .map(|takeback| {
+                let answer = if takeback.accepted { "accepted" } else { "declined" };
+                format!("Takeback by {:?}: {} ({})", takeback.by, takeback.moves.join(" "), answer)
+            })
+            .collect();
+
+        if comments.is_empty() {
+            return None;
+        }
+        Some(comments.join(", "))
+    }
+
+    // TOUCH-MOVE
+
+    /*
+    Over-the-board touch-move rule: a piece the side to move touches has to be moved, if it has a
+    legal move, and an opponent's piece it touches has to be captured, if it can be. Touching both
+    means capturing the touched piece with the own piece when that's possible, otherwise just
+    moving the own piece. A piece that is only straightened on its square has to be announced with
+    adjust() instead, then touching it doesn't commit to anything. Only the first touch of each kind
+    counts.
+    */
+
+    // Turn the touch-move rule on or off, forgetting pieces touched so far.
+    pub fn set_touch_move(&mut self, enabled: bool) {
+        self.touch_move = enabled;
+        self.touched = Touched::default();
+    }
+
+    pub fn touch_move(&self) -> bool {
+        self.touch_move
+    }
+
+    pub fn touched(&self) -> Touched {
+        self.touched
+    }
+
+    // The side to move touches the piece on square.
+    pub fn touch(&mut self, square: i8) -> Result<Touched, GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if !(0..64).contains(&square) || self.state.board.piece_at(square).is_none() {
+            return Err(GameError::NoPiece(square));
+        }
+        if !self.touch_move {
+            return Ok(self.touched);
+        }
+
+        if self.touched.adjusted & 1<<square != 0 {
+            return Ok(self.touched);
+        }
+
+        let legal_moves = self.legal_moves();
+
+        if read_cur_square(square, &self.state) {
+            if self.touched.piece.is_none() && legal_moves.iter().any(|mv| mv.drop.is_none() && mv.from == square) {
+                self.touched.piece = Some(square);
+            }
+        } else if self.touched.target.is_none() && legal_moves.iter().any(|mv| self.captures(mv, square)) {
+            self.touched.target = Some(square);
+        }
+
+        Ok(self.touched)
+    }
+
+    // The side to move adjusts its piece on square ("j'adoube"), which isn't touching it: handling
+    // that piece commits to nothing until the next move.
+    pub fn adjust(&mut self, square: i8) -> Result<(), GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if !(0..64).contains(&square) || !read_cur_square(square, &self.state) {
+            return Err(GameError::NoPiece(square));
+        }
+        if self.touch_move {
+            self.touched.adjusted |= 1<<square;
+        }
+
+        Ok(())
+    }
+
+    // The touched square mv ignores, if touch-move is on and mv isn't allowed.
+    fn touch_violation(&self, mv: &Move) -> Option<i8> {
+
+        if !self.touch_move {
+            return None;
+        }
+
+        let moves_piece = |mv: &Move, square: i8| mv.drop.is_none() && mv.from == square;
+
+        match (self.touched.piece, self.touched.target) {
+            (Some(piece), Some(target)) => {
+                let can_capture = self.legal_moves().iter().any(|other| moves_piece(other, piece) && self.captures(other, target));
+                if !moves_piece(mv, piece) {
+                    return Some(piece);
+                }
+                if can_capture && !self.captures(mv, target) {
+                    return Some(target);
+                }
+                None
+            }
+            (Some(piece), None) if !moves_piece(mv, piece) => Some(piece),
+            (None, Some(target)) if !self.captures(mv, target) => Some(target),
+            _ => None,
+        }
+    }
+
+    // Whether mv captures the piece on square (en passant captures the pawn beside mv.to).
+    fn captures(&self, mv: &Move, square: i8) -> bool {
+
+        if mv.drop.is_some() {
+            return false;
+        }
+        if mv.to == square {
+            return true;
+        }
+
+        let en_passant = self.state.board.piece_at(mv.from).is_some_and(|(_, piece)| piece == 'P')
+            && self.state.en_passant_mask == 1<<mv.to;
+
+        en_passant && square == mv.to % 8 + mv.from / 8 * 8
+    }
+
+    // PREMOVES
+
+    // Queue mv for `color` while the opponent is thinking. mv only has to be pseudo-legal in the
+    // position expected when it's played: the current one with the earlier premoves made, as if
+    // the opponent passed. It's checked for full legality when its turn comes.
+    pub fn premove(&mut self, color: Color, mv: Move) -> Result<(), GameError> {
+
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        if color == self.state.side_to_move {
+            return Err(GameError::OwnTurn);
+        }
+
+        let mv = mv.normalized();
+        let expected = self.expected_position(color);
+
+        let mut moves = generate_pseudo_legal_moves(&expected);
+        moves.extend(self.variant.extra_moves(&expected));
+
+        if !self.variant.filter_moves(&expected, moves).contains(&mv) {
+            return Err(GameError::IllegalMove(mv));
+        }
+
+        self.premoves.push(mv);
+        Ok(())
+    }
+
+    pub fn premoves(&self) -> &[Move] {
+        &self.premoves
+    }
+
+    pub fn cancel_premoves(&mut self) {
+        self.premoves.clear();
+    }
+
+    // The position after side's queued premoves, with side to move again.
+    fn expected_position(&self, side: Color) -> GameState {
+
+        let mut expected = self.state.clone();
+
+        for mv in &self.premoves {
+            expected.side_to_move = side;
+            expected.en_passant_mask = 0; // the opponent didn't double push
+            expected = variant::next_state(self.variant.as_ref(), &expected, mv);
+        }
+
+        expected.side_to_move = side;
+        expected.en_passant_mask = 0;
+        expected
+    }
+
+    // Plays the first queued premove after the opponent moved, or drops the queue if it's illegal
+    // now (or the game is over).
+    pub(crate) fn play_premove(&mut self) -> Option<Premove> {
+
+        if self.premoves.is_empty() {
+            return None;
+        }
+
+        let mv = self.premoves.remove(0);
+
+        if self.outcome.is_some() {
+            self.premoves.clear();
+            return None;
+        }
+
+        match self.play_move(mv) {
+            Ok(played) => Some(Premove::Played(Box::new(played))),
+            Err(_) => {
+                self.premoves.clear();
+                Some(Premove::Cancelled(mv))
+            }
+        }
+    }
+
+    // CLOCK
+
+    // Attach a clock to the game, it starts running for the side to move.
+    pub fn set_clock(&mut self, mut clock: ChessClock) {
+        if self.outcome.is_none() {
+            clock.start(self.state.side_to_move);
+        }
+        self.clock = Some(clock);
+    }
+
+    pub fn clock(&self) -> Option<&ChessClock> {
+        self.clock.as_ref()
+    }
+
+    pub(crate) fn stop_clock(&mut self) {
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+    }
+
+    // Put a piece into side's pocket from outside the game (Bughouse partners pass on captures).
+    pub(crate) fn give_piece(&mut self, side: Color, piece: char) {
+        self.state.pockets.get_or_insert_with(Pockets::default).get_mut(side).add(piece);
+        *self.positions.last_mut().unwrap() = self.state.repetition_key();
+    }
+
+    // Ends the game if the side to move has run out of time. Call it regularly while waiting for
+    // a move, a flag can fall without anyone moving.
+    pub fn check_time(&mut self) -> Option<Outcome> {
+
+        if self.outcome.is_some() {
+            return None;
+        }
+
+        let flagged = self.clock.as_ref()?.flagged()?;
+        let outcome = self.timeout_outcome(flagged);
+        self.finish(outcome).ok()
+    }
+
+    // Running out of time loses, unless the opponent couldn't win with any series of moves.
+    fn timeout_outcome(&self, flagged: Color) -> Outcome {
+        if self.variant.can_win(&self.state, flagged.opposite()) {
+            Outcome::win(flagged.opposite(), Termination::TimeForfeit)
+        } else {
+            Outcome::draw(Termination::TimeoutVsInsufficientMaterial)
+        }
+    }
+
+    fn finish(&mut self, outcome: Outcome) -> Result<Outcome, GameError> {
+        if self.outcome.is_some() {
+            return Err(GameError::GameOver);
+        }
+        self.outcome = Some(outcome);
+        self.draw_offer = None;
+        self.takeback_request = None;
+        self.premoves.clear();
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        Ok(outcome)
+    }
+
+    // How many times the current position has occurred.
+    fn repetitions(&self) -> usize {
+        let current = self.positions.last().unwrap();
+        self.positions.iter().filter(|&key| key == current).count()
+    }
+
+    // Endings decided by the position alone: the variant's rules first, then the repetition and
+    // move count draws.
+    fn board_outcome(&self) -> Option<Outcome> {
+
+        if let Some(outcome) = variant::outcome(self.variant.as_ref(), &self.state) {
+            return Some(outcome);
+        }
+        if self.repetitions() >= 5 {
+            return Some(Outcome::draw(Termination::FivefoldRepetition));
+        }
+        if self.state.halfmove_clock >= 150 {
+            return Some(Outcome::draw(Termination::SeventyFiveMoveRule));
+        }
+
+        None
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
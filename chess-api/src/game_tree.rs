@@ -0,0 +1,383 @@
+/*
+GameTree stores a game together with its side lines (variations), for analysis.
+
+Every node is a position reached by a move from its parent node. The root node is the start
+position and has no move. A node's first child is the main line, the other children are
+variations (alternatives to the main line move).
+
+Nodes are stored in a Vec and refer to each other by index (NodeId). Each node keeps the
+GameState after its move (like History keeps the GameStates before every move), so moving the
+cursor around never needs to replay moves, and `state()` is always the position at the cursor.
+*/
+
+use crate::notation::to_variant_san;
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::{next_state, playable_moves, Standard, Variant};
+use std::rc::Rc;
+
+pub type NodeId = usize;
+
+#[derive(Clone)]
+pub struct Node {
+    pub mv: Option<Move>, // None for the root
+    pub san: String,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>, // Numeric Annotation Glyphs, eg. 1 = "!", 2 = "?"
+    pub state: GameState,
+    pub ply: usize, // half-moves from the start, odd when black is to move
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+impl Node {
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+pub struct GameTree {
+    pub tags: Vec<(String, String)>, // PGN tag pairs, eg. ("White", "Carlsen")
+    nodes: Vec<Option<Node>>, // deleted nodes are set to None
+    cursor: NodeId,
+    variant: Rc<dyn Variant>,
+}
+
+impl GameTree {
+
+    pub fn new() -> Self {
+        Self::from_position(GameState::new())
+    }
+
+    pub fn from_position(state: GameState) -> Self {
+        Self::with_variant(Rc::new(Standard), state)
+    }
+
+    // A tree whose moves follow the rules of `variant`, starting from `state`.
+    pub fn with_variant(variant: Rc<dyn Variant>, state: GameState) -> Self {
+
+        let root = Node {
+            mv: None,
+            san: String::new(),
+            comment: None,
+            nags: Vec::new(),
+            ply: if state.side_to_move == Color::White { 0 } else { 1 },
+            state,
+            parent: None,
+            children: Vec::new(),
+        };
+
+        Self {
+            tags: Vec::new(),
+            nodes: vec![Some(root)],
+            cursor: 0,
+            variant,
+        }
+    }
+
+    pub fn variant(&self) -> &dyn Variant {
+        self.variant.as_ref()
+    }
+
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn cursor(&self) -> NodeId {
+        self.cursor
+    }
+
+    // The position at the cursor.
+    pub fn state(&self) -> &GameState {
+        &self.get(self.cursor).state
+    }
+
+    // The node with id, None if there is none (eg. it was deleted with delete_branch()).
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id)?.as_ref()
+    }
+
+    // For ids the tree handed out itself and that are still in it (the cursor, children, parents).
+    pub(crate) fn get(&self, id: NodeId) -> &Node {
+        self.nodes[id].as_ref().expect("node was deleted")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id].as_mut().expect("node was deleted")
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        id < self.nodes.len() && self.nodes[id].is_some()
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+
+    // MAKING MOVES
+
+    pub fn play(&mut self, mv: Move) -> bool {
+
+        /*
+        Plays mv from the cursor position and moves the cursor to the resulting node.
+        If the move is already in the tree it is followed, otherwise it's added as the main line
+        (if there are no moves from here yet) or as a new variation. Returns false if illegal.
+        */
+
+        let existing = self.get(self.cursor).children.iter()
+            .copied()
+            .find(|&child| self.get(child).mv.map(Move::normalized) == Some(mv.normalized()));
+
+        if let Some(child) = existing {
+            self.cursor = child;
+            return true;
+        }
+
+        let parent = self.get(self.cursor);
+
+        let variant = self.variant.as_ref();
+
+        let legal = playable_moves(variant, &parent.state).iter().any(|&legal| legal == mv.normalized());
+        if !legal {
+            return false;
+        }
+
+        let mv = mv.normalized();
+        let san = to_variant_san(variant, &mv, &parent.state);
+        let state = next_state(variant, &parent.state, &mv);
+
+        let node = Node {
+            mv: Some(mv),
+            san,
+            comment: None,
+            nags: Vec::new(),
+            state,
+            ply: parent.ply + 1,
+            parent: Some(self.cursor),
+            children: Vec::new(),
+        };
+
+        let id = self.nodes.len();
+        self.nodes.push(Some(node));
+        let cursor = self.cursor;
+        self.node_mut(cursor).children.push(id);
+        self.cursor = id;
+
+        true
+    }
+
+
+    // NAVIGATION
+
+    // Follow the main line one move. Returns false at the end of the line.
+    pub fn forward(&mut self) -> bool {
+        match self.get(self.cursor).children.first() {
+            Some(&child) => {
+                self.cursor = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Take back one move. Returns false at the root.
+    pub fn back(&mut self) -> bool {
+        match self.get(self.cursor).parent {
+            Some(parent) => {
+                self.cursor = parent;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.cursor = self.root();
+    }
+
+    pub fn go_to_end(&mut self) {
+        while self.forward() {}
+    }
+
+    pub fn go_to(&mut self, id: NodeId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.cursor = id;
+        true
+    }
+
+    // Moves that can be played from the cursor: the main line move first, then the variations.
+    pub fn next_moves(&self) -> Vec<Move> {
+        self.get(self.cursor).children.iter().filter_map(|&child| self.get(child).mv).collect()
+    }
+
+    // Enter variation number `index` from the cursor (0 is the main line, same as forward()).
+    pub fn enter_variation(&mut self, index: usize) -> bool {
+        match self.get(self.cursor).children.get(index) {
+            Some(&child) => {
+                self.cursor = child;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Leave the current variation: go back to the position where it branched off the line above it.
+    pub fn exit_variation(&mut self) -> bool {
+
+        let mut id = self.cursor;
+
+        while let Some(parent) = self.get(id).parent {
+            if self.get(parent).children[0] != id {
+                self.cursor = parent;
+                return true;
+            }
+            id = parent;
+        }
+
+        false
+    }
+
+    // Moves of the main line from the root.
+    pub fn mainline(&self) -> Vec<Move> {
+
+        let mut moves = Vec::new();
+        let mut id = self.root();
+
+        while let Some(&child) = self.get(id).children.first() {
+            moves.extend(self.get(child).mv);
+            id = child;
+        }
+
+        moves
+    }
+
+
+    // EDITING
+
+    // Make the line leading to `id` the main line, at every branching point on the way up.
+    pub fn promote_to_mainline(&mut self, id: NodeId) -> bool {
+
+        if !self.contains(id) {
+            return false;
+        }
+
+        let mut child = id;
+
+        while let Some(parent) = self.get(child).parent {
+            let siblings = &mut self.node_mut(parent).children;
+            let index = siblings.iter().position(|&sibling| sibling == child).unwrap();
+            let promoted = siblings.remove(index);
+            siblings.insert(0, promoted);
+            child = parent;
+        }
+
+        true
+    }
+
+    // Move the variation starting at `id` one step up among its siblings.
+    pub fn promote_variation(&mut self, id: NodeId) -> bool {
+
+        if !self.contains(id) {
+            return false;
+        }
+
+        let parent = match self.get(id).parent {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        let siblings = &mut self.node_mut(parent).children;
+        let index = siblings.iter().position(|&sibling| sibling == id).unwrap();
+
+        if index == 0 {
+            return false;
+        }
+
+        siblings.swap(index, index - 1);
+        true
+    }
+
+    // Delete the node `id` and every move after it. If the cursor was in the deleted branch it
+    // moves to the node the branch started from. The root can't be deleted.
+    pub fn delete_branch(&mut self, id: NodeId) -> bool {
+
+        if !self.contains(id) {
+            return false;
+        }
+
+        let parent = match self.get(id).parent {
+            Some(parent) => parent,
+            None => return false,
+        };
+
+        // is the cursor inside the branch?
+        let mut walk = Some(self.cursor);
+        while let Some(node) = walk {
+            if node == id {
+                self.cursor = parent;
+                break;
+            }
+            walk = self.get(node).parent;
+        }
+
+        self.node_mut(parent).children.retain(|&child| child != id);
+
+        let mut to_delete = vec![id];
+        while let Some(node) = to_delete.pop() {
+            if let Some(deleted) = self.nodes[node].take() {
+                to_delete.extend(deleted.children);
+            }
+        }
+
+        true
+    }
+
+    // Comments and NAGs: false if there's no node with id.
+    pub fn set_comment(&mut self, id: NodeId, comment: &str) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.node_mut(id).comment = if comment.is_empty() { None } else { Some(comment.to_string()) };
+        true
+    }
+
+    pub fn add_nag(&mut self, id: NodeId, nag: u8) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        let nags = &mut self.node_mut(id).nags;
+        if !nags.contains(&nag) {
+            nags.push(nag);
+        }
+        true
+    }
+
+    pub fn remove_nag(&mut self, id: NodeId, nag: u8) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.node_mut(id).nags.retain(|&n| n != nag);
+        true
+    }
+}
+
+impl Default for GameTree {
+    fn default() -> Self {
+        GameTree::new()
+    }
+}
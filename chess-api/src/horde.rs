@@ -0,0 +1,61 @@
+/*
+Horde: white has 36 pawns and no king against black's standard army.
+
+- White's pawns on the first rank may move two squares, like those on the second rank (see
+  legal_moves::WHITE_DOUBLE_PUSH).
+- White has no king, so white is never in check. Black wins by capturing all of white's pieces,
+  white wins by checkmating black.
+- Stalemate is a draw.
+*/
+
+use crate::bitboards::Board;
+use crate::outcome::{can_checkmate, Outcome, Termination};
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::{standard_outcome, Variant};
+
+pub const HORDE_FEN: &str = "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+
+pub struct Horde;
+
+impl Variant for Horde {
+
+    fn name(&self) -> &'static str {
+        "Horde"
+    }
+
+    fn start_position(&self) -> GameState {
+
+        let standard = Board::new();
+
+        let mut board = Board::empty();
+        board.white_pawns = 0xFFFFFFFF | 1<<33 | 1<<34 | 1<<37 | 1<<38; // ranks 1-4, b5, c5, f5, g5
+        board.white_occupied = board.white_pawns;
+        board.black_pawns = standard.black_pawns;
+        board.black_knights = standard.black_knights;
+        board.black_bishops = standard.black_bishops;
+        board.black_rooks = standard.black_rooks;
+        board.black_queens = standard.black_queens;
+        board.black_king = standard.black_king;
+        board.black_occupied = standard.black_occupied;
+
+        GameState::with_board(board)
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        if state.board.white_occupied == 0 {
+            return Some(Outcome::win(Color::Black, Termination::AllPiecesLost));
+        }
+
+        standard_outcome(state, legal_moves)
+    }
+
+    // black wins by taking the whole horde, which even a lone king might do
+    fn can_win(&self, state: &GameState, side: Color) -> bool {
+        match side {
+            Color::White => can_checkmate(&state.board, side),
+            Color::Black => true,
+        }
+    }
+}
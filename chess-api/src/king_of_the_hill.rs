@@ -0,0 +1,54 @@
+/*
+King of the Hill: standard chess, but a player also wins by bringing their king to one of the four
+centre squares (d4, e4, d5, e5).
+
+The king can't move into check, so it can only reach the centre with a legal move. Reaching the
+centre ends the game at once, so it's checked before checkmate and stalemate: a king move to the
+centre that also leaves the opponent without moves is a win by reaching the centre.
+Insufficient material doesn't apply, a lone king can still walk to the centre.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::Move;
+use crate::state::{Color, GameState};
+use crate::variant::{standard_outcome, Variant};
+
+pub const CENTRE: u64 = 1<<27 | 1<<28 | 1<<35 | 1<<36; // d4, e4, d5, e5
+
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        // only the side that just moved can have reached the centre
+        if in_centre(state, side.opposite()) {
+            return Some(Outcome::win(side.opposite(), Termination::KingInCentre));
+        }
+
+        // otherwise mate and stalemate as in standard chess, but a lone king can still win
+        standard_outcome(state, legal_moves)
+            .filter(|outcome| outcome.termination != Termination::InsufficientMaterial)
+    }
+
+    // a lone king can still walk to the centre
+    fn can_win(&self, _state: &GameState, _side: Color) -> bool {
+        true
+    }
+}
+
+fn in_centre(state: &GameState, side: Color) -> bool {
+
+    let king = match side {
+        Color::White => state.board.white_king,
+        Color::Black => state.board.black_king,
+    };
+
+    king & CENTRE != 0
+}
@@ -0,0 +1,312 @@
+/*
+Kriegspiel: neither player sees the opponent's pieces. Players try moves, and a referee, who sees
+the whole board, answers each attempt:
+
+- "nobody could have made that move": the move is impossible even without the opponent's pieces
+  on the board (eg. a bishop moving like a rook, or through its own pieces). Only the player
+  trying it hears this, it gives nothing away.
+- "illegal": the move would be possible on the player's own view of the board, but not on the
+  real one (eg. it leaves the king in check). Both players hear it, and the player tries again.
+- a pawn move to the last rank that names no piece promotes to a queen.
+- otherwise the move is played, and both players hear whether it captured ("capture on X"), gave
+  check and from where ("check on the file/rank/diagonal", "check by a knight"), and whether the
+  opponent now has pawn captures available.
+
+The referee keeps a history for each player, with their own moves and only the answers to the
+opponent's.
+*/
+
+use crate::fairy::{fairy_index, fairy_moves};
+use crate::fog_of_war::{hide_black_pieces, hide_white_pieces};
+use crate::game::{Game, GameError};
+use crate::legal_moves::{bishop_moves, knight_moves, pawn_attacks, queen_moves, rook_moves};
+use crate::notation::square_name;
+use crate::outcome::Outcome;
+use crate::perform_moves::{is_capture, is_legal, is_promotion, read_cur_square, Move};
+use crate::state::{Color, GameState};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheckDirection {
+    File,
+    Rank,
+    Diagonal,
+    Knight, // or a fairy piece jumping like one
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Announcement {
+    Impossible, // "nobody could have made that move"
+    Illegal,
+    Capture(i8), // the square the capture was made on
+    Check(CheckDirection),
+    PawnCapturesAvailable, // for the side to move next
+}
+
+impl Announcement {
+
+    // What the referee says.
+    pub fn text(self) -> String {
+        match self {
+            Announcement::Impossible => String::from("nobody could have made that move"),
+            Announcement::Illegal => String::from("illegal"),
+            Announcement::Capture(square) => format!("capture on {}", square_name(square)),
+            Announcement::Check(CheckDirection::File) => String::from("check on the file"),
+            Announcement::Check(CheckDirection::Rank) => String::from("check on the rank"),
+            Announcement::Check(CheckDirection::Diagonal) => String::from("check on the diagonal"),
+            Announcement::Check(CheckDirection::Knight) => String::from("check by a knight"),
+            Announcement::PawnCapturesAvailable => String::from("pawn captures available"),
+        }
+    }
+}
+
+// One move attempt, as a player knows it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Attempt {
+    pub side: Color, // who tried the move
+    pub mv: Option<Move>, // None for the opponent's attempts
+    pub answers: Vec<Announcement>,
+}
+
+impl Attempt {
+
+    // Whether the move was played. A played move may have no answers at all.
+    pub fn played(&self) -> bool {
+        !self.answers.iter().any(|answer| matches!(answer, Announcement::Impossible | Announcement::Illegal))
+    }
+}
+
+pub struct Referee {
+    game: Game,
+    white_history: Vec<Attempt>,
+    black_history: Vec<Attempt>,
+}
+
+impl Referee {
+
+    pub fn new() -> Self {
+        Self::with_game(Game::new())
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Self, GameError> {
+        Ok(Self::with_game(Game::from_fen(fen)?))
+    }
+
+    fn with_game(game: Game) -> Self {
+        Self {
+            game,
+            white_history: Vec::new(),
+            black_history: Vec::new(),
+        }
+    }
+
+    // The whole game, as only the referee sees it.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.game.outcome()
+    }
+
+    pub fn history(&self, side: Color) -> &[Attempt] {
+        match side {
+            Color::White => &self.white_history,
+            Color::Black => &self.black_history,
+        }
+    }
+
+    // The position as side knows it: only side's own pieces.
+    pub fn view(&self, side: Color) -> GameState {
+        own_pieces(self.game.state(), side)
+    }
+
+    // The side to move tries mv, the referee answers.
+    pub fn attempt(&mut self, mv: Move) -> Result<Vec<Announcement>, GameError> {
+
+        if self.game.outcome().is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        let side = self.game.side_to_move();
+        let state = self.game.state().clone();
+
+        /* a pawn reaching the last rank without a piece named becomes a queen, so the attempt
+        isn't announced as illegal to both players */
+        let mv = match mv.promotion {
+            None if mv.drop.is_none() && is_promotion(mv.from, mv.to, &state) => Move { promotion: Some('Q'), ..mv },
+            _ => mv,
+        };
+
+        if !could_be_legal(&state, &mv) {
+            let answers = vec![Announcement::Impossible];
+            self.record(side, mv, &answers, false);
+            return Ok(answers);
+        }
+
+        let capture = is_capture(mv.from, mv.to, &state);
+
+        let played = match self.game.play(mv) {
+            Ok(played) => played,
+            Err(GameError::IllegalMove(_)) => {
+                let answers = vec![Announcement::Illegal];
+                self.record(side, mv, &answers, true);
+                return Ok(answers);
+            }
+            Err(error) => return Err(error),
+        };
+
+        let mut answers = Vec::new();
+
+        if capture {
+            answers.push(Announcement::Capture(played.mv.to));
+        }
+        for direction in check_directions(self.game.state(), side) {
+            answers.push(Announcement::Check(direction));
+        }
+        if self.game.outcome().is_none() && has_pawn_captures(&self.game) {
+            answers.push(Announcement::PawnCapturesAvailable);
+        }
+
+        self.record(side, played.mv, &answers, true);
+        Ok(answers)
+    }
+
+    // Saves the attempt in side's history, and in the opponent's without the move if they hear it.
+    fn record(&mut self, side: Color, mv: Move, answers: &[Announcement], public: bool) {
+
+        let own = Attempt { side, mv: Some(mv), answers: answers.to_vec() };
+        let other = Attempt { side, mv: None, answers: answers.to_vec() };
+
+        let (own_history, other_history) = match side {
+            Color::White => (&mut self.white_history, &mut self.black_history),
+            Color::Black => (&mut self.black_history, &mut self.white_history),
+        };
+
+        own_history.push(own);
+        if public {
+            other_history.push(other);
+        }
+    }
+}
+
+impl Default for Referee {
+    fn default() -> Self {
+        Referee::new()
+    }
+}
+
+// state with the opponent's pieces (and anything that would give them away) removed.
+fn own_pieces(state: &GameState, side: Color) -> GameState {
+
+    let mut view = state.clone();
+
+    match side {
+        Color::White => {
+            hide_black_pieces(u64::MAX, &mut view.board);
+            view.black_can_castle_kingside = false;
+            view.black_can_castle_queenside = false;
+        }
+        Color::Black => {
+            hide_white_pieces(u64::MAX, &mut view.board);
+            view.white_can_castle_kingside = false;
+            view.white_can_castle_queenside = false;
+        }
+    }
+    view.en_passant_mask = 0;
+
+    view
+}
+
+// Whether mv could be legal with the opponent's pieces anywhere: it has to be a move on the
+// player's own view of the board, or a pawn capturing diagonally.
+fn could_be_legal(state: &GameState, mv: &Move) -> bool {
+
+    let side = state.side_to_move;
+    let own = own_pieces(state, side);
+
+    if !read_cur_square(mv.from, &own) {
+        return false;
+    }
+
+    let target_mask: u64 = 1<<mv.to;
+    let is_pawn = own.board.piece_at(mv.from) == Some((side, 'P'));
+    let own_occupied = match side {
+        Color::White => own.board.white_occupied,
+        Color::Black => own.board.black_occupied,
+    };
+
+    if is_pawn && pawn_attacks(1<<mv.from, side, &own.board.geometry) & target_mask & !own_occupied != 0 {
+        return true;
+    }
+
+    is_legal(mv.from, mv.to, &own)
+}
+
+// Where each of side's pieces giving check stands, seen from the checked king.
+fn check_directions(state: &GameState, side: Color) -> Vec<CheckDirection> {
+
+    let board = &state.board;
+    let king = match side {
+        Color::White => board.black_king,
+        Color::Black => board.white_king,
+    };
+
+    let mut directions = Vec::new();
+
+    if king == 0 {
+        return directions;
+    }
+    let king_square = king.trailing_zeros() as i8;
+
+    for square in 0..64 {
+
+        let mask: u64 = 1<<square;
+
+        let attacks = match board.piece_at(square) {
+            Some((color, piece)) if color == side => match piece {
+                'P' => pawn_attacks(mask, side, &board.geometry),
+                'N' => knight_moves(mask, state, side),
+                'B' => bishop_moves(mask, state, side),
+                'R' => rook_moves(mask, state, side),
+                'Q' => queen_moves(mask, state, side),
+                _ => match fairy_index(piece) {
+                    Some(index) => fairy_moves(mask, index, state, side),
+                    None => continue,
+                },
+            },
+            _ => continue,
+        };
+
+        if attacks & king == 0 {
+            continue;
+        }
+
+        let files = (square % 8 - king_square % 8).abs();
+        let ranks = (square / 8 - king_square / 8).abs();
+
+        // a checking knight (or fairy leaper) is never on a line with the king
+        let direction = if files == 0 {
+            CheckDirection::File
+        } else if ranks == 0 {
+            CheckDirection::Rank
+        } else if files == ranks {
+            CheckDirection::Diagonal
+        } else {
+            CheckDirection::Knight
+        };
+        directions.push(direction);
+    }
+
+    directions
+}
+
+// Whether the side to move has a legal pawn capture.
+fn has_pawn_captures(game: &Game) -> bool {
+
+    let state = game.state();
+    let side = state.side_to_move;
+
+    game.legal_moves().iter().any(|mv| {
+        state.board.piece_at(mv.from) == Some((side, 'P')) && is_capture(mv.from, mv.to, state)
+    })
+}
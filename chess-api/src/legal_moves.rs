@@ -6,22 +6,24 @@ all squares occupied by the specific piece.
 - `BOARD.white_occupied` and `BOARD.black_occupied` track which squares are occupied by each color.
 - Used to determine if a move is legal (cannot move to a square already occupied by own piece, allow moving to a square
 occupied by opponent's piece (representing a capture)).
+- `BOARD.duck` (Duck chess) blocks both sides: no piece can move onto or through it, and it can't be captured.
+- `BOARD.geometry` is the size of the board, no piece moves off the squares of smaller boards (minichess).
 
 - MOVE is a global state variable indicating whose turn it is.
 - 0 = white to move, 1 = black to move
 - used to determine which pieces belong to opponent
 */
 
-use crate::{perform_moves::checked_squares, state::GameState, state::Color};
+use crate::{bitboards::Geometry, perform_moves::castle, perform_moves::checked_squares, perform_moves::is_check, state::GameState, state::Color};
 
 // Masking: https://www.chessprogramming.org/Square_Mapping_Considerations
-const FILE_A: u64 = 0b100000001000000010000000100000001000000010000000100000001;
-const FILE_H: u64 = 0b1000000010000000100000001000000010000000100000001000000010000000;
-const FILE_B: u64 = 0b1000000010000000100000001000000010000000100000001000000010;
-const FILE_G: u64 = 0b100000001000000010000000100000001000000010000000100000001000000;
+// The edge files come from the board's Geometry, the last file isn't the h-file on smaller boards.
 
-const SECOND_RANK: u64 = 0b1111111100000000; 
-const SEVENTH_RANK: u64 = 0xFF000000000000;
+// Squares pawns may move two squares from: the second (seventh) rank, and also the first (eighth)
+// rank, where pawns never stand in standard chess, but do in Horde. Used by Geometry::STANDARD, pawn
+// moves read the squares from Board.geometry.
+pub const WHITE_DOUBLE_PUSH: u64 = 0xFFFF;
+pub const BLACK_DOUBLE_PUSH: u64 = 0xFFFF000000000000;
 
 pub fn knight_moves(pos: u64, state: &GameState, side: Color) -> u64 { // masking inspo: https://www.chessprogramming.org/Knight_Pattern
 
@@ -29,10 +31,13 @@ pub fn knight_moves(pos: u64, state: &GameState, side: Color) -> u64 { // maskin
 
     let board = &state.board;
 
-    let not_a_file = !FILE_A;
-    let not_ab_file = !(FILE_A | FILE_B);
-    let not_h_file = !FILE_H;
-    let not_gh_file = !(FILE_G | FILE_H);
+    let geometry = &board.geometry;
+    let last = geometry.files - 1;
+
+    let not_a_file = !geometry.file(0);
+    let not_ab_file = !(geometry.file(0) | geometry.file(1));
+    let not_h_file = !geometry.file(last);
+    let not_gh_file = !(geometry.file(last - 1) | geometry.file(last));
 
     targeted_squares |= (pos & not_h_file) << 17; // up twice, right once
     targeted_squares |= (pos & not_gh_file) << 10; // up once, right twice
@@ -50,12 +55,85 @@ pub fn knight_moves(pos: u64, state: &GameState, side: Color) -> u64 { // maskin
         Color::Black => targeted_squares &= !board.black_occupied,
     }
 
-    return targeted_squares;
+    targeted_squares &= !board.duck & board.geometry.squares();
+
+    targeted_squares
     
 }
 
 
 
+pub fn leaper_moves(pos: u64, a: i8, b: i8, state: &GameState, side: Color) -> u64 {
+
+    // Jumps of a squares along one axis and b along the other, in every direction (fairy pieces,
+    // eg. the camel is a (1, 3) leaper).
+
+    let mut targeted_squares: u64 = 0u64;
+
+    let board = &state.board;
+    let own_occupied = match side {
+        Color::White => board.white_occupied,
+        Color::Black => board.black_occupied,
+    };
+
+    let jumps = [(a, b), (b, a), (-a, b), (-b, a), (a, -b), (b, -a), (-a, -b), (-b, -a)];
+
+    let mut pieces = pos;
+
+    while pieces != 0 {
+        let square = pieces.trailing_zeros() as i8;
+        pieces &= pieces - 1;
+
+        for (files, ranks) in jumps {
+
+            let file = square % 8 + files;
+            let rank = square / 8 + ranks;
+
+            if (0..board.geometry.files).contains(&file) && (0..board.geometry.ranks).contains(&rank) {
+                targeted_squares |= 1 << (rank * 8 + file);
+            }
+        }
+    }
+
+    targeted_squares & !(own_occupied | board.duck)
+}
+
+pub fn king_pattern(pos: u64, geometry: &Geometry) -> u64 {
+
+    // All squares of the board next to the squares in pos, whatever is on them.
+
+    let not_a_file = !geometry.file(0);
+    let not_h_file = !geometry.file(geometry.files - 1);
+
+    let mut pattern: u64 = 0u64;
+
+    pattern |= (pos & not_a_file) << 7; // up once, left once
+    pattern |= pos << 8; // one once
+    pattern |= (pos & not_h_file) << 9; // up once, right once
+    pattern |= (pos & not_a_file) >> 1;
+    pattern |= (pos & not_h_file) << 1;
+    pattern |= (pos & not_a_file) >> 9;
+    pattern |= pos >> 8;
+    pattern |= (pos & not_h_file) >> 7;
+
+    pattern & geometry.squares()
+}
+
+pub fn pawn_attacks(pos: u64, side: Color, geometry: &Geometry) -> u64 {
+
+    // The diagonal squares of the board in front of the pawns in pos, whatever is on them.
+
+    let not_a_file = !geometry.file(0);
+    let not_h_file = !geometry.file(geometry.files - 1);
+
+    let attacks = match side {
+        Color::White => ((pos & not_a_file) << 7) | ((pos & not_h_file) << 9),
+        Color::Black => ((pos & not_a_file) >> 9) | ((pos & not_h_file) >> 7),
+    };
+
+    attacks & geometry.squares()
+}
+
 pub fn king_moves(pos: u64, state: &GameState, side: Color, include_castling: bool) -> u64 { // add more checks later (for check, checkmate etc.)
 
     let mut targeted_squares: u64 = 0u64;
@@ -67,14 +145,7 @@ pub fn king_moves(pos: u64, state: &GameState, side: Color, include_castling: bo
         Color::Black => board.black_occupied,
     };
 
-    targeted_squares |= (pos & !FILE_A) << 7 & !own_occupied; // up once, left once
-    targeted_squares |= pos << 8 & !own_occupied; // one once
-    targeted_squares |= (pos & !FILE_H) << 9 & !own_occupied; // up once, right once
-    targeted_squares |= (pos & !FILE_A) >> 1 & !own_occupied;
-    targeted_squares |= (pos & !FILE_H) << 1 & !own_occupied;
-    targeted_squares |= (pos & !FILE_A) >> 9 & !own_occupied;
-    targeted_squares |= pos >> 8 & !own_occupied;
-    targeted_squares |= (pos & !FILE_H) >> 7 & !own_occupied;
+    targeted_squares |= king_pattern(pos, &board.geometry) & !(own_occupied | board.duck);
 
 
     if include_castling && state.side_to_move == side {   
@@ -89,7 +160,7 @@ pub fn king_moves(pos: u64, state: &GameState, side: Color, include_castling: bo
     }
 
 
-    return targeted_squares
+    targeted_squares
 
 }
 
@@ -99,82 +170,98 @@ pub fn castling(cur_square: i8, state: &GameState) -> u64{
     /*
     castling() checks:
     - that the states white_can_castle_kingside, black_can_castle_queenside etc. are true,
-    - that the king's position is it's original position, and
-    - that the squares in between the rook and the king are unoccupied and unchecked,
-    - that the king is not in check 
+    - that the king's position is it's original position and the rook is still on its square, and
+    - that the squares the king and rook pass through (up to and including their final squares) are
+    unoccupied, except by the king and that rook,
+    - that the squares the king passes through are unchecked (including its start square, so the
+    king is not in check), and the king isn't in check after the rook has moved either.
+
+    In standard chess the target square is the king's final square (g1/c1). In Chess960 it is the
+    rook's square, since the king might only move one square or not at all.
 
     We DON'T pass a side: Color, because castling can only be done if you're not in check -> not relevant
     to check if the opponent's king can castle when checking for eg. checkmate, because that's never valid.
     */
 
     let mut targets = 0;
-    let occupied = state.board.white_occupied | state.board.black_occupied;
+    let side = state.side_to_move;
+    let opponent_color = side.opposite();
+    let occupied = state.board.white_occupied | state.board.black_occupied | state.board.duck;
 
-    let opponent_color = state.side_to_move.opposite();
-
-    if state.side_to_move == Color::White && cur_square == 4{
-
-
-        if state.white_can_castle_kingside {
+    let (kingside_right, queenside_right, own_rooks) = match side {
+        Color::White => (state.white_can_castle_kingside, state.white_can_castle_queenside, state.board.white_rooks),
+        Color::Black => (state.black_can_castle_kingside, state.black_can_castle_queenside, state.board.black_rooks),
+    };
 
-            // check squares in between
-            let in_between_mask = 1<<5 | 1<<6;
+    for (right, kingside) in [(kingside_right, true), (queenside_right, false)] {
 
-            if (in_between_mask & occupied) == 0 {
+        if !right {
+            continue;
+        }
 
-                if (checked_squares(state, opponent_color) & (in_between_mask | 1<<4)) == 0{
-                    targets |= 1<<6;
-                }
-            }
+        let (king_start, rook_start, king_dest, rook_dest) = castling_squares(side, kingside, state);
 
+        if cur_square != king_start || own_rooks & 1<<rook_start == 0 {
+            continue;
         }
 
-        if state.white_can_castle_queenside {
+        let king_path = squares_between(king_start, king_dest);
+        let rook_path = squares_between(rook_start, rook_dest);
+        let must_be_empty = (king_path | rook_path) & !(1<<king_start | 1<<rook_start);
 
-            let in_between_mask = 1<<1 | 1<<2 | 1<<3;
-
-            if (in_between_mask & occupied) == 0 {
+        if (must_be_empty & occupied) != 0 {
+            continue;
+        }
 
-                if (checked_squares(state, opponent_color) & (in_between_mask | 1<<4)) == 0{
-                    targets |= 1<<2;
-                }
-            }
+        if (checked_squares(state, opponent_color) & king_path) != 0 {
+            continue;
         }
-    }
-    
 
-    else if state.side_to_move == Color::Black && cur_square == 60 {
+        let target = if state.chess960 { rook_start } else { king_dest };
 
-        if state.black_can_castle_kingside {
+        // the rook leaving its square can open a line to the king (only possible in Chess960)
+        let mut after = state.clone();
+        castle(king_start, target, &mut after);
+        if is_check(&after, opponent_color) {
+            continue;
+        }
 
-            let in_between_mask = 1<<61 | 1<<62;
+        targets |= 1<<target;
+    }
 
-            if (in_between_mask & occupied) == 0 {
+    targets
 
-                if (checked_squares(state, opponent_color) & (in_between_mask | 1<<60)) == 0 {
-                    targets |= 1<<62;
-                }
-            }
-        }
-        
+}
 
-        if state.black_can_castle_queenside {
+pub fn castling_squares(side: Color, kingside: bool, state: &GameState) -> (i8, i8, i8, i8) {
 
-            let in_between_mask = 1<<59 | 1<<58 | 1<<57;
+    /*
+    (king start, rook start, king final, rook final) squares for castling. The king always ends on
+    the g-file (kingside) or c-file (queenside) and the rook next to it on the f- or d-file.
+    */
 
-            if (in_between_mask & occupied) == 0 {
+    let files = state.castling_files(side);
+    let back_rank = match side {
+        Color::White => 0,
+        Color::Black => (state.board.geometry.ranks - 1) * 8,
+    };
 
-                if (checked_squares(state, opponent_color) & (in_between_mask | 1<<60)) == 0 {
-                    targets |= 1 << 58;
-                }
-            }
-        }  
+    if kingside {
+        (back_rank + files.king, back_rank + files.kingside_rook, back_rank + 6, back_rank + 5)
+    } else {
+        (back_rank + files.king, back_rank + files.queenside_rook, back_rank + 2, back_rank + 3)
     }
+}
 
-    return targets;
+fn squares_between(from: i8, to: i8) -> u64 {
 
+    // all squares from `from` to `to` on the same rank, both included
+    let (low, high) = if from < to { (from, to) } else { (to, from) };
+
+    (low..=high).fold(0, |mask, square| mask | 1<<square)
 }
 
+#[allow(clippy::needless_return)]
 pub fn rook_moves(pos: u64, state: &GameState, side: Color) -> u64 {
     let mut targeted_squares = 0u64;
     let mut rooks = pos;
@@ -200,7 +287,7 @@ pub fn helper_rook_moves(square: i8, state: &GameState, side: Color) -> u64{
         Color::Black => false,
     };
 
-    let unoccupied = !(board.white_occupied | board.black_occupied);
+    let unoccupied = !(board.white_occupied | board.black_occupied | board.duck);
 
 
     // bitmasking 
@@ -211,30 +298,30 @@ pub fn helper_rook_moves(square: i8, state: &GameState, side: Color) -> u64{
 
     for n in 1..8-cur_row { // iterate upwards
 
-        if (cur_mask << 8*n & unoccupied) != 0 {
-            targeted_squares |= cur_mask << 8*n & unoccupied;
+        if (cur_mask << (8*n) & unoccupied) != 0 {
+            targeted_squares |= cur_mask << (8*n) & unoccupied;
         }
         else if white_to_move{
-            targeted_squares |= cur_mask << 8*n & board.black_occupied;
+            targeted_squares |= cur_mask << (8*n) & board.black_occupied;
             break;
         }
         else if !white_to_move {
-            targeted_squares |= cur_mask << 8*n & board.white_occupied;
+            targeted_squares |= cur_mask << (8*n) & board.white_occupied;
             break;
         }
     }
 
-    for n in 1..cur_row+2 { // iterate downwards
+    for n in 1..cur_row+1 { // iterate downwards
 
-        if (cur_mask >> 8*n & unoccupied) != 0 {
-            targeted_squares |= cur_mask >> 8*n & unoccupied;
+        if (cur_mask >> (8*n) & unoccupied) != 0 {
+            targeted_squares |= cur_mask >> (8*n) & unoccupied;
         }
         else if white_to_move {
-            targeted_squares |= cur_mask >> 8*n & board.black_occupied;
+            targeted_squares |= cur_mask >> (8*n) & board.black_occupied;
             break;
         }
         else if !white_to_move {
-            targeted_squares |= cur_mask >> 8*n & board.white_occupied;
+            targeted_squares |= cur_mask >> (8*n) & board.white_occupied;
             break;
         }
     }
@@ -257,7 +344,7 @@ pub fn helper_rook_moves(square: i8, state: &GameState, side: Color) -> u64{
         }
     }
 
-    for n in 1..8-cur_col+1 { // iterate left
+    for n in 1..cur_col+1 { // iterate left
 
         let new_mask = cur_mask >> n;
 
@@ -275,13 +362,14 @@ pub fn helper_rook_moves(square: i8, state: &GameState, side: Color) -> u64{
         }
     }
 
-    return targeted_squares;
+    targeted_squares & state.board.geometry.squares()
         
 
 }
 
 
 
+#[allow(clippy::needless_return)]
 pub fn bishop_moves(pos: u64, state: &GameState, side: Color) -> u64 {
     let mut targeted_squares = 0u64;
     let mut bishops = pos;
@@ -307,7 +395,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
         Color::Black => false,
     };
 
-    let unoccupied = !(board.white_occupied | board.black_occupied);
+    let unoccupied = !(board.white_occupied | board.black_occupied | board.duck);
 
     // bitmasking 
 
@@ -325,7 +413,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
     for n in 1..max_up_right { // iterate up, right
 
         // 8*n+n = 9*n
-        let new_mask = cur_mask << 9*n;
+        let new_mask = cur_mask << (9*n);
 
         if (new_mask & unoccupied) != 0 {
             targeted_squares |= new_mask & unoccupied;
@@ -344,7 +432,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
 
         // 8*n - n = 7*n
 
-        let new_mask = cur_mask << 7*n;
+        let new_mask = cur_mask << (7*n);
 
         if (new_mask & unoccupied) != 0 {
             targeted_squares |= new_mask & unoccupied;
@@ -355,6 +443,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
         }
         else if !white_to_move {
             targeted_squares |= new_mask & board.white_occupied;
+            break;
         }
     }
 
@@ -362,7 +451,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
 
         //  7*n
 
-        let new_mask = cur_mask >> 7*n ;
+        let new_mask = cur_mask >> (7*n) ;
 
         if (new_mask & unoccupied) != 0 {
             targeted_squares |= new_mask & unoccupied;
@@ -373,6 +462,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
         }
         else if !white_to_move {
             targeted_squares |= new_mask & board.white_occupied;
+            break;
         }
     }
 
@@ -380,7 +470,7 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
 
         // 9*n
 
-        let new_mask = cur_mask >> 9*n;
+        let new_mask = cur_mask >> (9*n);
 
         if (new_mask & unoccupied) != 0 {
             targeted_squares |= new_mask & unoccupied;
@@ -395,10 +485,11 @@ pub fn helper_bishop_moves(square: i8, state: &GameState, side: Color) -> u64 {
         }
     }
 
-    return targeted_squares;
+    targeted_squares & state.board.geometry.squares()
 }
 
 
+#[allow(clippy::needless_return)]
 pub fn queen_moves(square: u64, state: &GameState, side: Color) -> u64 { // combine bishop&rook moves
 
     return rook_moves(square, state, side) | bishop_moves(square, state, side);
@@ -412,7 +503,7 @@ pub fn pawn_moves(pos: u64, state: &GameState, side: Color) ->u64 {
 
     let board = &state.board;
 
-    let unoccupied = !(board.white_occupied | board.black_occupied);
+    let unoccupied = !(board.white_occupied | board.black_occupied | board.duck) & board.geometry.squares();
 
     // Only use en_passant_mask if we're determining the current player's possible moves
     // Otherwise they could capture their own pawns
@@ -427,26 +518,24 @@ pub fn pawn_moves(pos: u64, state: &GameState, side: Color) ->u64 {
         Color::White => {
 
             let one_step = (pos << 8) & unoccupied;
-            let two_steps = (((SECOND_RANK << 8) & one_step) << 8) & unoccupied;
-            let capture_left = (board.black_occupied | en_passant_mask) & ((pos&!FILE_A) << 7);
-            let capture_right = (board.black_occupied | en_passant_mask) & ((pos&!FILE_H) << 9);
+            let two_steps = (((board.geometry.white_double_push << 8) & one_step) << 8) & unoccupied;
+            let captures = (board.black_occupied | en_passant_mask) & pawn_attacks(pos, side, &board.geometry);
 
-            targeted_squares |= one_step | two_steps | capture_left | capture_right;
+            targeted_squares |= one_step | two_steps | captures;
 
         }
 
         Color::Black => {
 
             let one_step = (pos >> 8) & unoccupied;
-            let two_steps = (((SEVENTH_RANK >> 8) & one_step) >> 8) & unoccupied;
-            let capture_left = (board.white_occupied | en_passant_mask) & ((pos&!FILE_A) >> 9);
-            let capture_right = (board.white_occupied | en_passant_mask) & ((pos&!FILE_H) >> 7);
+            let two_steps = (((board.geometry.black_double_push >> 8) & one_step) >> 8) & unoccupied;
+            let captures = (board.white_occupied | en_passant_mask) & pawn_attacks(pos, side, &board.geometry);
 
-            targeted_squares |= one_step | two_steps | capture_left | capture_right;
+            targeted_squares |= one_step | two_steps | captures;
 
         }
     };
 
-    return targeted_squares;
+    targeted_squares
 
 }
\ No newline at end of file
@@ -1,19 +1,51 @@
 // multiple source files https://www.youtube.com/watch?v=lx5r7yzl1Ps
 
 pub mod bitboards;
+pub mod fairy;
 pub mod perform_moves;
 pub mod legal_moves;
 pub mod state;
 pub mod visualize;
+pub mod notation;
+pub mod game_tree;
+pub mod pgn;
+pub mod fen;
+pub mod outcome;
+pub mod game;
+pub mod clock;
+pub mod adjudication;
+pub mod variant;
+pub mod king_of_the_hill;
+pub mod three_check;
+pub mod atomic;
+pub mod antichess;
+pub mod crazyhouse;
+pub mod horde;
+pub mod racing_kings;
+pub mod bughouse;
+pub mod fog_of_war;
+pub mod kriegspiel;
+pub mod duck_chess;
+pub mod seirawan;
+pub mod minichess;
+pub mod odds;
 
 
 #[cfg(test)] // kompileras endast när tester körs
+#[allow(clippy::bool_assert_comparison, clippy::bool_comparison, unused_assignments)]
 mod tests {
 
     use super::*;
     use crate::state::GameState;
     use crate::state::Color;
     use crate::state::History;
+    use crate::perform_moves::Move;
+    use crate::game_tree::GameTree;
+    use crate::game::{DrawClaim, Game, GameError};
+    use crate::outcome::{GameResult, Outcome, Termination};
+    use crate::clock::{ChessClock, ManualClock, TimeControl, Timing};
+    use std::rc::Rc;
+    use std::time::Duration;
 
     #[test]
     fn test_pawn_moves() {
@@ -323,4 +355,1175 @@ mod tests {
 
     }
 
+    #[test]
+    fn test_history_unbounded() {
+
+        let mut history = History::new();
+
+        for _ in 0..500 {
+            history.push(GameState::new());
+        }
+        assert_eq!(history.len(), 500);
+
+        let mut history = History::with_retention(10);
+
+        for _ in 0..500 {
+            history.push(GameState::new());
+        }
+        assert_eq!(history.len(), 10);
+        assert_eq!(history.ply(), 500);
+        assert!(history.truncate_to(5).is_none()); // dropped from the history
+
+        // redoing keeps the limit too
+        let state = history.undo(GameState::new()).unwrap();
+        assert_eq!(history.len(), 9);
+        history.redo(state).unwrap();
+        assert_eq!(history.len(), 10);
+        assert_eq!(history.ply(), 500);
+    }
+
+    #[test]
+    fn test_undo_redo() {
+
+        let mut state = GameState::new();
+        let mut history = History::new();
+
+        perform_moves::make_move(12, 28, &mut state, &mut history, true); // e2-e4
+        perform_moves::make_move(52, 36, &mut state, &mut history, true); // e7-e5
+
+        state = history.undo(state).unwrap();
+        assert_eq!(state.side_to_move, Color::Black);
+        assert_eq!(history.redo_len(), 1);
+
+        // an illegal attempt doesn't throw away the redo stack
+        let result = perform_moves::make_move(60, 44, &mut state, &mut history, true);
+        assert_eq!(result, false);
+        assert!(history.can_redo());
+
+        state = history.redo(state).unwrap();
+        assert_eq!(state.side_to_move, Color::White);
+        assert_ne!(state.board.black_pawns & 1<<36, 0);
+        assert_eq!(history.len(), 2);
+
+        // a new move after undo clears the redo stack
+        state = history.undo(state).unwrap();
+        perform_moves::make_move(51, 35, &mut state, &mut history, true); // d7-d5
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn test_truncate_to() {
+
+        let mut state = GameState::new();
+        let mut history = History::new();
+
+        perform_moves::make_move(12, 28, &mut state, &mut history, true); // e2-e4
+        perform_moves::make_move(52, 36, &mut state, &mut history, true); // e7-e5
+        perform_moves::make_move(6, 21, &mut state, &mut history, true); // Ng1-f3
+
+        // branch off after 1. e4
+        state = history.truncate_to(1).unwrap();
+        assert_eq!(history.ply(), 1);
+        assert_eq!(state.side_to_move, Color::Black);
+
+        let move_made = perform_moves::make_move(50, 34, &mut state, &mut history, true); // c7-c5
+        assert_eq!(move_made, true);
+        assert_eq!(history.ply(), 2);
+    }
+
+    #[test]
+    fn test_san() {
+
+        let state = GameState::new();
+
+        assert_eq!(notation::to_san(&Move::new(6, 21), &state), "Nf3");
+        assert_eq!(notation::parse_san("e4", &state), Some(Move::new(12, 28)));
+        assert_eq!(notation::parse_san("Ke2", &state), None);
+        assert_eq!(notation::square_name(63), "h8");
+        assert_eq!(notation::parse_square("e4"), Some(28));
+    }
+
+    #[test]
+    fn test_game_tree_variations() {
+
+        let mut tree = GameTree::new();
+
+        assert!(tree.play(Move::new(12, 28))); // e4
+        assert!(tree.play(Move::new(52, 36))); // e5
+        assert!(tree.play(Move::new(6, 21))); // Nf3
+        assert!(!tree.play(Move::new(0, 8))); // Ra2?? not legal
+
+        // side line 2... c5 instead of 2... e5
+        tree.go_to_start();
+        tree.forward();
+        assert!(tree.play(Move::new(50, 34)));
+        let sicilian = tree.cursor();
+        assert_eq!(tree.state().side_to_move, Color::White);
+
+        tree.back();
+        assert_eq!(tree.next_moves(), vec![Move::new(52, 36), Move::new(50, 34)]);
+        assert!(tree.enter_variation(1));
+        assert_eq!(tree.cursor(), sicilian);
+
+        tree.promote_to_mainline(sicilian);
+        assert_eq!(tree.mainline(), vec![Move::new(12, 28), Move::new(50, 34)]);
+
+        // deleting the old main line moves nothing but the branch
+        tree.go_to_start();
+        tree.forward();
+        tree.enter_variation(1);
+        tree.forward();
+        let e5 = tree.node(tree.cursor()).unwrap().parent().unwrap();
+        assert!(tree.delete_branch(e5));
+        assert_eq!(tree.node(tree.cursor()).unwrap().ply, 1);
+        assert_eq!(tree.next_moves(), vec![Move::new(50, 34)]);
+
+        // the deleted nodes are gone, editing them is refused
+        assert!(tree.node(e5).is_none() && tree.node(1000).is_none());
+        assert!(!tree.go_to(e5));
+        assert!(!tree.set_comment(e5, "gone"));
+        assert!(!tree.add_nag(e5, 1));
+    }
+
+    #[test]
+    fn test_pgn_round_trip() {
+
+        let text = "[Event \"Club night\"]\n[Result \"*\"]\n\n\
+            1. e4 e5 {open game} 2. Nf3 (2. f4!? exf4 3. Bc4) 2... Nc6 $1 3. Bb5 a6 *";
+
+        let tree = pgn::read_pgn(text).unwrap();
+
+        assert_eq!(tree.tag("Event"), Some("Club night"));
+        assert_eq!(tree.mainline().len(), 6);
+
+        let exported = pgn::write_pgn(&tree);
+        assert!(exported.contains("1. e4 e5 {open game} 2. Nf3 (2. f4 $5 exf4 3. Bc4) 2... Nc6 $1 3. Bb5 a6 *"));
+
+        let again = pgn::read_pgn(&exported).unwrap();
+        assert_eq!(pgn::write_pgn(&again), exported);
+
+        assert_eq!(pgn::read_pgn("1. e4 e5 2. Ke3").err(), Some(pgn::PgnError::IllegalMove("Ke3".to_string())));
+        assert_eq!(pgn::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 12e4").err(), Some(pgn::PgnError::IllegalMove("12e4".to_string())));
+
+        // castling written with zeros
+        let tree = pgn::read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 *").unwrap();
+        assert_eq!(tree.mainline()[6], Move::new(4, 6));
+        assert!(pgn::write_pgn(&tree).contains("4. O-O Nf6"));
+
+        // a comment opening a variation belongs to the variation's first move
+        let mut tree = pgn::read_pgn("1. e4 ({Alt} 1. d4) e5 *").unwrap();
+        assert_eq!(tree.get(tree.root()).comment, None);
+        tree.forward();
+        assert_eq!(tree.get(tree.cursor()).comment, None);
+        tree.back();
+        tree.enter_variation(1);
+        assert_eq!(tree.get(tree.cursor()).comment.as_deref(), Some("Alt"));
+        assert!(pgn::write_pgn(&tree).contains("1. e4 (1. d4 {Alt}) 1... e5 *"));
+
+        // tag values are quoted, a "]" inside the quotes doesn't end the tag
+        let tree = pgn::read_pgn("[Event \"a]b \\\"c\\\"\"]\n\n1. e4 *").unwrap();
+        assert_eq!(tree.tag("Event"), Some("a]b \"c\""));
+        assert_eq!(tree.mainline().len(), 1);
+        for text in ["[Event \"x", "[Event \"x\"", "[Event x]\n\n1. e4 *"] {
+            assert_eq!(pgn::read_pgn(text).err(), Some(pgn::PgnError::UnclosedTag));
+        }
+    }
+
+    #[test]
+    fn test_fen() {
+
+        let state = GameState::new();
+        assert_eq!(fen::to_fen(&state), fen::START_FEN);
+
+        let mut state = GameState::new();
+        let mut history = History::new();
+        perform_moves::make_move(12, 28, &mut state, &mut history, true); // e2-e4
+        assert_eq!(fen::to_fen(&state), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        let parsed = fen::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(fen::to_fen(&parsed), fen::to_fen(&state));
+        assert_eq!(fen::from_fen("8/8/8 w - -").err(), Some(fen::FenError::InvalidPlacement));
+
+        // runs of empty squares are single digits that stay on the board, "~" only follows a piece
+        for placement in ["99999999999999999/8/8/8/8/8/8/8", "44/8/8/8/8/8/8/8", "k7p/8/8/8/8/8/8/7K", "0k7/8/8/8/8/8/8/7K", "9~/8/8/8/8/8/8/8", "k6~1/8/8/8/8/8/8/7K", "kQ~~6/8/8/8/8/8/8/7K"] {
+            let fen = format!("{} w - - 0 1", placement);
+            assert_eq!(fen::from_fen(&fen).err(), Some(fen::FenError::InvalidPlacement));
+        }
+    }
+
+    #[test]
+    fn test_game_session() {
+
+        let mut game = Game::new();
+
+        // fool's mate
+        game.play(Move::new(13, 21)).unwrap(); // f3
+        game.play(Move::new(52, 36)).unwrap(); // e5
+        assert_eq!(game.play(Move::new(0, 8)), Err(GameError::IllegalMove(Move::new(0, 8))));
+        game.play(Move::new(14, 30)).unwrap(); // g4
+        let played = game.play(Move::new(59, 31)).unwrap(); // Qh4#
+
+        assert_eq!(played.san, "Qh4#");
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::Black, Termination::Checkmate)));
+        assert_eq!(game.play(Move::new(12, 20)), Err(GameError::GameOver));
+        assert!(game.legal_moves().is_empty());
+
+        assert_eq!(game.undo(), Ok(Move::new(59, 31)));
+        assert_eq!(game.outcome(), None);
+        assert!(game.legal_moves().contains(&Move::new(59, 31)));
+
+        game.resign(Color::White).unwrap();
+        assert_eq!(game.undo(), Err(GameError::GameOver));
+        assert!(game.to_pgn().contains("1. f3 e5 2. g4 0-1"));
+    }
+
+    #[test]
+    fn test_draws() {
+
+        let mut game = Game::new();
+
+        assert_eq!(game.accept_draw(Color::Black), Err(GameError::NoDrawOffer));
+        game.offer_draw(Color::White).unwrap();
+        game.play(Move::new(6, 21)).unwrap(); // Nf3, white's own offer still stands
+        assert_eq!(game.draw_offer(), Some(Color::White));
+        game.play(Move::new(62, 45)).unwrap(); // Nf6 declines it
+        assert_eq!(game.draw_offer(), None);
+
+        // knights back and forth
+        for _ in 0..2 {
+            game.play(Move::new(21, 6)).unwrap();
+            game.play(Move::new(45, 62)).unwrap();
+            game.play(Move::new(6, 21)).unwrap();
+            game.play(Move::new(62, 45)).unwrap();
+        }
+        assert_eq!(game.draw_claims(), vec![DrawClaim::ThreefoldRepetition]);
+        assert_eq!(game.claim_draw(DrawClaim::ThreefoldRepetition).unwrap().result, GameResult::Draw);
+
+        let game = Game::from_fen("8/8/4k3/8/8/3BK3/8/8 w - - 0 1").unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::draw(Termination::InsufficientMaterial)));
+    }
+
+    #[test]
+    fn test_clock_timings() {
+
+        let time = ManualClock::new();
+        let secs = Duration::from_secs;
+
+        // Fischer: 60 s + 2 s
+        let mut clock = ChessClock::new(TimeControl::fischer(secs(60), secs(2)), Box::new(time.clone()));
+        clock.start(Color::White);
+        time.advance(secs(10));
+        assert_eq!(clock.remaining(Color::White), secs(50));
+        clock.press(Color::White).unwrap();
+        assert_eq!(clock.remaining(Color::White), secs(52));
+        assert_eq!(clock.running(), Some(Color::Black));
+
+        // Bronstein: at most the delay is given back
+        let mut clock = ChessClock::new(TimeControl::sudden_death(secs(60), Timing::Bronstein(secs(5))), Box::new(time.clone()));
+        clock.start(Color::White);
+        time.advance(secs(3));
+        clock.press(Color::White).unwrap();
+        assert_eq!(clock.remaining(Color::White), secs(60));
+        time.advance(secs(8));
+        clock.press(Color::Black).unwrap();
+        assert_eq!(clock.remaining(Color::Black), secs(57));
+
+        // simple delay: the first 5 s aren't counted
+        let mut clock = ChessClock::new(TimeControl::sudden_death(secs(60), Timing::SimpleDelay(secs(5))), Box::new(time.clone()));
+        clock.start(Color::White);
+        time.advance(secs(4));
+        assert_eq!(clock.remaining(Color::White), secs(60));
+        time.advance(secs(4));
+        clock.press(Color::White).unwrap();
+        assert_eq!(clock.remaining(Color::White), secs(57));
+    }
+
+    #[test]
+    fn test_clock_stages() {
+
+        let time = ManualClock::new();
+        let mins = |m: u64| Duration::from_secs(m * 60);
+
+        // 2 moves in 90 minutes, then 30 minutes for the rest of the game
+        let control = TimeControl::new()
+            .then(Some(2), mins(90), Timing::NoIncrement)
+            .then(None, mins(30), Timing::NoIncrement);
+        let mut clock = ChessClock::new(control, Box::new(time.clone()));
+
+        clock.start(Color::White);
+        assert_eq!(clock.moves_to_go(Color::White), Some(2));
+        time.advance(mins(10));
+        clock.press(Color::White).unwrap();
+        clock.press(Color::Black).unwrap();
+        assert_eq!(clock.moves_to_go(Color::White), Some(1));
+        time.advance(mins(10));
+        clock.press(Color::White).unwrap();
+
+        assert_eq!(clock.remaining(Color::White), mins(100));
+        assert_eq!(clock.moves_to_go(Color::White), None);
+
+        time.advance(mins(200));
+        assert_eq!(clock.flagged(), Some(Color::Black));
+        assert_eq!(clock.press(Color::Black), Err(Color::Black));
+
+        // a stage of 0 moves is for the rest of the game
+        let control = TimeControl::new().then(Some(0), mins(5), Timing::NoIncrement);
+        let mut clock = ChessClock::new(control, Box::new(time.clone()));
+        clock.start(Color::White);
+        assert_eq!(clock.moves_to_go(Color::White), None);
+        clock.press(Color::White).unwrap();
+        assert_eq!(clock.time_control().stages[0].moves, None);
+    }
+
+    #[test]
+    fn test_flag_fall() {
+
+        let time = ManualClock::new();
+        let control = TimeControl::fischer(Duration::from_secs(60), Duration::ZERO);
+
+        let mut game = Game::new();
+        game.set_clock(ChessClock::new(control.clone(), Box::new(time.clone())));
+        game.play(Move::new(12, 28)).unwrap();
+        time.advance(Duration::from_secs(61));
+
+        assert_eq!(game.check_time(), Some(Outcome::win(Color::White, Termination::TimeForfeit)));
+        assert_eq!(game.play(Move::new(52, 36)), Err(GameError::GameOver));
+
+        // white only has a king left, so black running out of time is a draw
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/r7/4K3 b - - 0 1").unwrap();
+        game.set_clock(ChessClock::new(control, Box::new(time.clone())));
+        time.advance(Duration::from_secs(61));
+
+        assert_eq!(game.play(Move::new(8, 0)), Err(GameError::GameOver));
+        assert_eq!(game.outcome(), Some(Outcome::draw(Termination::TimeoutVsInsufficientMaterial)));
+    }
+
+    #[test]
+    fn test_chess960_start_positions() {
+
+        let standard = GameState::new_chess960(518).unwrap();
+        assert_eq!(fen::to_fen(&standard), fen::START_FEN);
+
+        let state = GameState::new_chess960(0).unwrap();
+        assert_eq!(fen::to_fen(&state), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(fen::to_shredder_fen(&state), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+        assert!(GameState::new_chess960(960).is_none());
+
+        // every start position has bishops on opposite colors and the king between the rooks
+        for index in 0..960 {
+            let board = GameState::new_chess960(index).unwrap().board;
+            assert_eq!((board.white_bishops & 0x55).count_ones(), 1);
+            let king = board.white_king.trailing_zeros();
+            assert!(board.white_rooks.trailing_zeros() < king && 63 - board.white_rooks.leading_zeros() > king);
+        }
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+
+        // king on b1, rooks on a1 and g1 (X-FEN "GA", the g-rook isn't the outermost)
+        let mut state = fen::from_fen("1r4k1/8/8/8/8/8/8/RK4RR w GA - 0 1").unwrap();
+        assert!(state.chess960);
+        assert_eq!(state.white_castling_files.kingside_rook, 6);
+        assert_eq!(fen::to_fen(&state), "1r4k1/8/8/8/8/8/8/RK4RR w GQ - 0 1");
+
+        let mut history = History::new();
+
+        // queenside: the king goes to c1 and the rook to d1, but the black rook on b8 attacks b1
+        assert_eq!(perform_moves::is_legal(1, 0, &state), false);
+
+        // kingside: king b1 -> g1, rook g1 -> f1, written as the king taking its own rook
+        let move_made = perform_moves::make_move(1, 6, &mut state, &mut history, true);
+        assert_eq!(move_made, false); // in check on b1
+
+        let mut state = fen::from_fen("6k1/8/8/8/8/8/8/RK4RR w GA - 0 1").unwrap();
+        assert_eq!(notation::to_san(&Move::new(1, 6), &state), "O-O");
+        let move_made = perform_moves::make_move(1, 6, &mut state, &mut history, true);
+        assert_eq!(move_made, true);
+        assert_eq!(state.board.white_king, 1<<6);
+        assert_eq!(state.board.white_rooks, 1<<0 | 1<<5 | 1<<7);
+        assert_eq!(state.board.white_occupied, 1<<0 | 1<<5 | 1<<6 | 1<<7);
+        assert!(!state.white_can_castle_queenside);
+    }
+
+    // a test variant: only pawns may move
+    struct PawnsOnly;
+
+    impl variant::Variant for PawnsOnly {
+
+        fn name(&self) -> &'static str {
+            "Pawns Only"
+        }
+
+        fn start_position(&self) -> GameState {
+            fen::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap()
+        }
+
+        fn filter_moves(&self, state: &GameState, mut moves: Vec<Move>) -> Vec<Move> {
+            moves.retain(|mv| state.board.piece_at(mv.from).map(|(_, piece)| piece) == Some('P'));
+            moves
+        }
+    }
+
+    #[test]
+    fn test_variant_hooks() {
+
+        let mut game = Game::with_variant(Rc::new(PawnsOnly));
+
+        assert_eq!(game.legal_moves(), vec![Move::new(12, 20), Move::new(12, 28)]);
+        assert_eq!(game.play(Move::new(4, 5)), Err(GameError::IllegalMove(Move::new(4, 5))));
+
+        // black only has a king, so it can't move: stalemate
+        game.play(Move::new(12, 28)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::draw(Termination::Stalemate)));
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Variant \"Pawns Only\"]"));
+        assert!(!pgn.contains("[FEN")); // the variant's own start position
+    }
+
+    #[test]
+    fn test_standard_variant() {
+
+        // standard chess through the Variant driver agrees with make_move()
+        let mut state = GameState::new();
+        let mut history = History::new();
+
+        assert!(variant::make_move(&variant::Standard, &Move::new(13, 21), &mut state, &mut history));
+        assert!(variant::make_move(&variant::Standard, &Move::new(52, 36), &mut state, &mut history));
+        assert!(!variant::make_move(&variant::Standard, &Move::new(52, 36), &mut state, &mut history));
+        assert!(variant::make_move(&variant::Standard, &Move::new(14, 30), &mut state, &mut history));
+        assert!(variant::make_move(&variant::Standard, &Move::new(59, 31), &mut state, &mut history));
+
+        assert_eq!(history.len(), 4);
+        assert_eq!(variant::legal_moves(&variant::Standard, &state), perform_moves::generate_legal_moves(&state));
+        assert_eq!(variant::outcome(&variant::Standard, &state), Some(Outcome::win(Color::Black, Termination::Checkmate)));
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+
+        // Kd4 reaches the centre and stalemates black, reaching the centre comes first
+        let mut game = Game::with_variant_from_fen(Rc::new(king_of_the_hill::KingOfTheHill), "k7/8/1Q6/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert_eq!(game.outcome(), None); // a lone king isn't insufficient material here
+        game.play(Move::new(19, 27)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::KingInCentre)));
+
+        // the king can't walk into check on a centre square
+        let game = Game::with_variant_from_fen(Rc::new(king_of_the_hill::KingOfTheHill), "k2r4/8/1Q6/8/8/3K4/8/8 w - - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::new(19, 27)));
+
+        // away from the centre, mate is still mate
+        let mut game = Game::with_variant_from_fen(Rc::new(king_of_the_hill::KingOfTheHill), "k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        game.play(Move::new(7, 63)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::Checkmate)));
+
+        // taking the checking pawn en passant gets out of check
+        let state = fen::from_fen("8/8/k7/1pPn4/K7/8/b7/1n6 w - b6 0 2").unwrap();
+        assert_eq!(variant::legal_moves(&king_of_the_hill::KingOfTheHill, &state), vec![Move::new(34, 41)]);
+        assert_eq!(variant::outcome(&king_of_the_hill::KingOfTheHill, &state), None);
+
+        // the Variant tag picks the rules when reading PGN
+        let mut tree = pgn::read_pgn("[Variant \"King of the Hill\"]\n\n1. e3 e6 2. Ke2 Ke7 3. Kd3 Kd6 4. Kd4 1-0").unwrap();
+        assert_eq!(tree.variant().name(), "King of the Hill");
+        tree.go_to_end();
+        assert_eq!(variant::outcome(tree.variant(), tree.state()), Some(Outcome::win(Color::White, Termination::KingInCentre)));
+        assert!(pgn::read_pgn("1. e3 e6 2. Ke2 Ke7 3. Kd3 Kd6 4. Kd4 Kc6 *").is_ok());
+        assert!(pgn::read_pgn("[Variant \"King of the Hill\"]\n\n1. e3 e6 2. Ke2 Ke7 3. Kd3 Kd6 4. Kd4 Kc6 *").is_err());
+    }
+
+    #[test]
+    fn test_three_check() {
+
+        let game = Game::with_variant(Rc::new(three_check::ThreeCheck));
+        assert!(game.fen().ends_with("KQkq - 0 1 +0+0"));
+
+        let mut game = Game::with_variant_from_fen(Rc::new(three_check::ThreeCheck), "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0").unwrap();
+        assert_eq!(game.outcome(), None);
+
+        let played = game.play(Move::new(0, 56)).unwrap(); // Ra8+, the third check
+        assert_eq!(played.san, "Ra8+");
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::ThreeChecks)));
+        assert_eq!(game.fen(), "R3k3/8/8/8/8/8/8/4K3 b - - 1 1 +3+0");
+        assert!(game.legal_moves().is_empty());
+
+        // undo takes the check back
+        game.undo().unwrap();
+        assert_eq!(game.state().checks, Some(state::CheckCounter { white: 2, black: 0 }));
+
+        // the same position with a different number of checks isn't a repetition
+        let mut other = game.state().clone();
+        other.checks = Some(state::CheckCounter { white: 1, black: 0 });
+        assert_ne!(other.repetition_key(), game.state().repetition_key());
+
+        assert_eq!(fen::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +x+0").err(), Some(fen::FenError::InvalidCheckCount));
+    }
+
+    #[test]
+    fn test_atomic() {
+
+        // 3. Nxf7 explodes the king on e8, the bishop and knight next to f7, and the knight itself
+        let mut game = Game::with_variant(Rc::new(atomic::Atomic));
+        for mv in [Move::new(6, 21), Move::new(48, 40), Move::new(21, 38), Move::new(40, 32)] {
+            game.play(mv).unwrap();
+        }
+        let before = game.fen();
+        game.play(Move::new(38, 53)).unwrap();
+        assert_eq!(game.fen(), "rnbq3r/1pppp1pp/8/p7/8/8/PPPPPPPP/RNBQKB1R b KQ - 0 3");
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::KingExploded)));
+
+        // undo brings the exploded pieces back
+        game.undo().unwrap();
+        assert_eq!(game.fen(), before);
+
+        // kings can't capture, and may stand next to each other
+        let game = Game::with_variant_from_fen(Rc::new(atomic::Atomic), "8/8/8/8/3k4/4K3/4n3/8 w - - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::new(20, 12))); // Kxe2
+        assert!(game.legal_moves().contains(&Move::new(20, 19))); // Kd3
+
+        // exploding the enemy king wins even with the own king in check
+        let mut game = Game::with_variant_from_fen(Rc::new(atomic::Atomic), "4k3/4p2R/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        game.play(Move::new(55, 52)).unwrap(); // Rxe7
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::KingExploded)));
+    }
+
+    #[test]
+    fn test_antichess() {
+
+        // 1. e3 b5, now Bxb5 is compulsory
+        let mut game = Game::with_variant(Rc::new(antichess::Antichess));
+        assert!(game.fen().starts_with("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - -"));
+        game.play(Move::new(12, 20)).unwrap();
+        game.play(Move::new(49, 33)).unwrap();
+        assert_eq!(game.legal_moves(), vec![Move::new(5, 33)]);
+
+        // the king can be taken, and losing the last piece wins
+        let mut game = Game::with_variant_from_fen(Rc::new(antichess::Antichess), "8/8/8/8/8/8/8/R1k5 w - - 0 1").unwrap();
+        assert_eq!(game.play(Move::new(0, 2)).unwrap().san, "Rxc1");
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::Black, Termination::AllPiecesLost)));
+
+        // promotion to a king
+        let mut game = Game::with_variant_from_fen(Rc::new(antichess::Antichess), "8/P7/8/8/8/8/8/7k w - - 0 1").unwrap();
+        assert_eq!(game.play(Move::with_promotion(48, 56, 'K')).unwrap().san, "a8=K");
+        assert_eq!(game.state().board.piece_at(56), Some((Color::White, 'K')));
+        assert_eq!(game.state().board.white_pawns, 0);
+
+        // a stalemated player wins
+        let game = Game::with_variant_from_fen(Rc::new(antichess::Antichess), "7k/8/8/8/8/p7/P7/8 w - - 0 1").unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::Stalemate)));
+    }
+
+    #[test]
+    fn test_crazyhouse() {
+
+        // 1. e4 d5 2. exd5 Qxd5, both sides have a pawn in hand
+        let mut game = Game::with_variant(Rc::new(crazyhouse::Crazyhouse));
+        for mv in [Move::new(12, 28), Move::new(51, 35), Move::new(28, 35), Move::new(59, 35)] {
+            game.play(mv).unwrap();
+        }
+        assert_eq!(game.fen(), "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+
+        // pawns can't be dropped on the first rank
+        assert!(!game.legal_moves().iter().any(|mv| mv.drop == Some('P') && mv.to < 8));
+
+        let drop = notation::parse_variant_san(game.variant(), "@e4", game.state()).unwrap();
+        assert_eq!(drop, Move::new_drop('P', 28));
+        assert_eq!(game.play(drop).unwrap().san, "P@e4");
+        assert_eq!(game.state().pockets.unwrap().white.pawns, 0);
+
+        // a promoted piece goes into the pocket as a pawn
+        let mut game = Game::with_variant_from_fen(Rc::new(crazyhouse::Crazyhouse), "k7/8/8/8/8/8/8/Rq~2K3[] w - - 0 1").unwrap();
+        game.play(Move::new(0, 1)).unwrap();
+        assert_eq!(game.fen(), "k7/8/8/8/8/8/8/1R2K3[P] b - - 0 1");
+
+        // not mate: a knight can be dropped in between
+        let game = Game::with_variant_from_fen(Rc::new(crazyhouse::Crazyhouse), "R6k/6pp/8/8/8/8/8/K7[n] b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), None);
+        assert!(game.legal_moves().contains(&Move::new_drop('N', 62)));
+
+        let game = Game::with_variant_from_fen(Rc::new(crazyhouse::Crazyhouse), "R6k/6pp/8/8/8/8/8/K7[] b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::Checkmate)));
+    }
+
+    #[test]
+    fn test_horde() {
+
+        let game = Game::with_variant(Rc::new(horde::Horde));
+        assert_eq!(game.fen(), horde::HORDE_FEN);
+        assert_eq!(game.outcome(), None);
+
+        // the Variant tag implies the start position, SetUp and FEN are only for other positions
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Variant \"Horde\"]") && !pgn.contains("[SetUp"));
+        assert_eq!(fen::to_fen(pgn::read_pgn(&pgn).unwrap().state()), horde::HORDE_FEN);
+
+        // also when the start position is given in a FEN written differently
+        let fen = horde::HORDE_FEN.rsplitn(3, ' ').last().unwrap();
+        let game = Game::with_variant_from_fen(Rc::new(horde::Horde), fen).unwrap();
+        assert_eq!(game.start_fen(), horde::HORDE_FEN);
+        assert!(!game.to_pgn().contains("[SetUp"));
+
+        // a pawn on the first rank can move two squares
+        let mut game = Game::with_variant_from_fen(Rc::new(horde::Horde), "4k3/8/8/8/8/8/8/P7 w - - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&Move::new(0, 16)));
+        game.play(Move::new(0, 16)).unwrap();
+        assert_eq!(game.fen(), "4k3/8/8/8/8/P7/8/8 b - a2 0 1");
+        assert!(game.to_pgn().contains("[FEN \"4k3/8/8/8/8/8/8/P7 w - - 0 1\"]"));
+
+        // white loses when its last piece is taken
+        let mut game = Game::with_variant_from_fen(Rc::new(horde::Horde), "4k3/8/8/8/8/8/1p6/P7 b - - 0 1").unwrap();
+        game.play(Move::with_promotion(9, 0, 'Q')).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::Black, Termination::AllPiecesLost)));
+
+        // black's lone king can still take the horde, so white running out of time loses
+        let mut game = Game::with_variant_from_fen(Rc::new(horde::Horde), "4k3/8/8/8/8/8/P7/8 w - - 0 1").unwrap();
+        let time = ManualClock::new();
+        game.set_clock(ChessClock::new(TimeControl::sudden_death(Duration::from_secs(60), Timing::NoIncrement), Box::new(time.clone())));
+        time.advance(Duration::from_secs(61));
+        assert_eq!(game.check_time(), Some(Outcome::win(Color::Black, Termination::TimeForfeit)));
+    }
+
+    #[test]
+    fn test_racing_kings() {
+
+        let game = Game::with_variant(Rc::new(racing_kings::RacingKings));
+        assert_eq!(game.fen(), racing_kings::RACING_KINGS_FEN);
+
+        // giving check isn't allowed
+        let game = Game::with_variant_from_fen(Rc::new(racing_kings::RacingKings), "8/8/8/8/8/k7/8/K6R w - - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::new(7, 23))); // Rh3+
+        assert!(game.legal_moves().contains(&Move::new(7, 15))); // Rh2
+
+        // white gets there first, but black can still tie
+        let mut game = Game::with_variant_from_fen(Rc::new(racing_kings::RacingKings), "8/1k4K1/8/8/8/8/8/8 w - - 0 1").unwrap();
+        game.play(Move::new(54, 62)).unwrap();
+        assert_eq!(game.outcome(), None);
+        game.play(Move::new(49, 57)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::draw(Termination::KingReachedGoal)));
+
+        // black is too far away
+        let mut game = Game::with_variant_from_fen(Rc::new(racing_kings::RacingKings), "8/6K1/1k6/8/8/8/8/8 w - - 0 1").unwrap();
+        game.play(Move::new(54, 62)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::win(Color::White, Termination::KingReachedGoal)));
+
+        // a lone king can still win the race, so running out of time against it loses
+        let mut game = Game::with_variant_from_fen(Rc::new(racing_kings::RacingKings), "8/8/8/8/8/8/kr6/6K1 b - - 0 1").unwrap();
+        let time = ManualClock::new();
+        game.set_clock(ChessClock::new(TimeControl::sudden_death(Duration::from_secs(60), Timing::NoIncrement), Box::new(time.clone())));
+        time.advance(Duration::from_secs(61));
+        assert_eq!(game.check_time(), Some(Outcome::win(Color::White, Termination::TimeForfeit)));
+    }
+
+    #[test]
+    fn test_bughouse() {
+
+        use crate::bughouse::{BughouseGame, Team, BOARD_A, BOARD_B};
+
+        let mut game = BughouseGame::new();
+        game.set_players(BOARD_A, "Anna", "Ben");
+        game.set_players(BOARD_B, "Carl", "Dora");
+
+        let time_a = ManualClock::new();
+        let time_b = ManualClock::new();
+        let control = || TimeControl::sudden_death(Duration::from_secs(60), Timing::NoIncrement);
+        game.set_clocks(ChessClock::new(control(), Box::new(time_a.clone())), ChessClock::new(control(), Box::new(time_b.clone())));
+
+        // the pawn taken on board A goes to Anna's partner Dora, black on board B
+        for mv in [Move::new(12, 28), Move::new(51, 35), Move::new(28, 35)] {
+            game.play(BOARD_A, mv).unwrap();
+        }
+        assert_eq!(game.board(BOARD_A).state().pockets.unwrap().white.pawns, 0);
+        assert_eq!(game.board(BOARD_B).state().pockets.unwrap().black.pawns, 1);
+
+        game.play(BOARD_B, Move::new(12, 28)).unwrap();
+        assert_eq!(game.play(BOARD_B, Move::new_drop('P', 35)).unwrap().san, "P@d5");
+
+        // Ben's flag falls, which ends board B as well
+        time_a.advance(Duration::from_secs(61));
+        let outcome = game.check_time().unwrap();
+        assert_eq!(outcome.winner, Some(Team::A));
+        assert_eq!(outcome.board, BOARD_A);
+        assert_eq!(game.board(BOARD_B).clock().unwrap().running(), None);
+        assert_eq!(game.play(BOARD_B, Move::new(11, 27)), Err(GameError::GameOver));
+
+        let bpgn = game.to_bpgn();
+        assert!(bpgn.contains("[WhiteB \"Carl\"]"));
+        assert!(bpgn.ends_with("1A. e4 1a. d5 2A. exd5 1B. e4 1b. P@d5 1-0\n"));
+
+        // a bare king with a knight in the pocket still wins on time
+        let board = Rc::new(crate::bughouse::BughouseBoard);
+        let mut game = Game::with_variant_from_fen(board, "4k3/8/8/8/8/8/r7/4K3[N] b - - 0 1").unwrap();
+        let time = ManualClock::new();
+        game.set_clock(ChessClock::new(control(), Box::new(time.clone())));
+        time.advance(Duration::from_secs(61));
+        assert_eq!(game.check_time(), Some(Outcome::win(Color::White, Termination::TimeForfeit)));
+    }
+
+    #[test]
+    fn test_fog_of_war() {
+
+        // white sees its pieces and everything up to the fourth rank, none of black's pieces
+        let state = GameState::new();
+        assert_eq!(fog_of_war::visible_squares(&state, Color::White), 0xFFFFFFFF);
+        assert_eq!(fen::to_fen(&fog_of_war::view(&state, Color::White)), "8/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1");
+
+        // the queen sees up the d-file, but not the king on e8
+        let mut game = Game::with_variant_from_fen(Rc::new(fog_of_war::FogOfWar), "4k3/8/8/8/8/8/3Q4/4K3 b - - 0 1").unwrap();
+        let view = fog_of_war::view(game.state(), Color::White);
+        assert_eq!(view.board.black_king, 0);
+
+        // the king may walk into the queen's line, and capturing it wins
+        assert_eq!(game.play(Move::new(60, 51)).unwrap().san, "Kd7");
+        assert!(fog_of_war::visible_squares(game.state(), Color::White) & 1<<51 != 0);
+        let played = game.play(Move::new(11, 51)).unwrap();
+        assert_eq!(played.game_over, Some(Outcome::win(Color::White, Termination::KingCaptured)));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_kriegspiel_referee() {
+
+        use crate::kriegspiel::{Announcement, CheckDirection, Referee};
+
+        let mut referee = Referee::new();
+
+        assert_eq!(referee.attempt(Move::new(12, 28)).unwrap(), vec![]); // e4
+        // the bishop is blocked by its own pawn, only black hears about it
+        assert_eq!(referee.attempt(Move::new(58, 49)).unwrap(), vec![Announcement::Impossible]);
+        assert_eq!(referee.attempt(Move::new(51, 35)).unwrap(), vec![Announcement::PawnCapturesAvailable]); // d5
+        assert_eq!(referee.attempt(Move::new(5, 33)).unwrap(), vec![Announcement::Check(CheckDirection::Diagonal)]); // Bb5+
+        assert_eq!(referee.attempt(Move::new(62, 45)).unwrap(), vec![Announcement::Illegal]); // Nf6, still in check
+        assert_eq!(referee.attempt(Move::new(50, 42)).unwrap(), vec![Announcement::PawnCapturesAvailable]); // c6
+        let answers = referee.attempt(Move::new(28, 35)).unwrap(); // exd5
+        assert_eq!(answers, vec![Announcement::Capture(35), Announcement::PawnCapturesAvailable]);
+        assert_eq!(answers[0].text(), "capture on d5");
+
+        // each player knows their own moves, and only the answers to the opponent's
+        let white = referee.history(Color::White);
+        let black = referee.history(Color::Black);
+        assert_eq!(white.len(), 6);
+        assert_eq!(black.len(), 7);
+        assert_eq!(white[1].mv, None);
+        assert_eq!(black[1].mv, Some(Move::new(58, 49)));
+        assert!(!black[1].played());
+        assert!(black[2].played());
+
+        assert_eq!(referee.view(Color::White).board.black_occupied, 0);
+
+        // a promotion without a piece becomes a queen, nothing is announced as illegal
+        let mut referee = Referee::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(referee.attempt(Move::new(48, 56)).unwrap(), vec![Announcement::Check(CheckDirection::Rank)]);
+        assert_eq!(referee.game().state().board.white_queens, 1<<56);
+        assert_eq!(referee.history(Color::White)[0].mv, Some(Move::with_promotion(48, 56, 'Q')));
+    }
+
+    #[test]
+    fn test_duck_chess() {
+
+        // every move needs a duck square
+        let mut game = Game::with_variant(Rc::new(duck_chess::DuckChess));
+        assert!(game.play(Move::new(12, 28)).is_err());
+
+        let mv = notation::parse_variant_san(game.variant(), "e4,e5", game.state()).unwrap();
+        assert_eq!(mv, Move::new(12, 28).with_duck(36));
+        assert_eq!(game.play(mv).unwrap().san, "e4,e5");
+        assert_eq!(game.fen(), "rnbqkbnr/pppppppp/8/4*3/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        // the duck blocks the pawn, and has to move on
+        assert!(!game.legal_moves().iter().any(|mv| mv.from == 52 && mv.to == 36));
+        assert!(!game.legal_moves().iter().any(|mv| mv.duck == Some(36)));
+
+        // nothing moves through the duck
+        let game = Game::with_variant_from_fen(Rc::new(duck_chess::DuckChess), "4k3/8/8/8/8/8/8/R2*K3 w - - 0 1").unwrap();
+        assert!(game.legal_moves().iter().any(|mv| mv.from == 0 && mv.to == 2));
+        assert!(!game.legal_moves().iter().any(|mv| mv.from == 0 && mv.to == 3));
+        assert!(!game.legal_moves().iter().any(|mv| mv.from == 4 && mv.to == 3));
+
+        // also for black's sliders, on both diagonals
+        let game = Game::with_variant_from_fen(Rc::new(duck_chess::DuckChess), "q3k3/8/2*5/8/8/5*2/8/4K2b b - - 0 1").unwrap();
+        assert!(game.legal_moves().iter().any(|mv| mv.from == 7 && mv.to == 14));
+        assert!(!game.legal_moves().iter().any(|mv| mv.from == 7 && (mv.to == 21 || mv.to == 28)));
+        assert!(game.legal_moves().iter().any(|mv| mv.from == 56 && mv.to == 49));
+        assert!(!game.legal_moves().iter().any(|mv| mv.from == 56 && (mv.to == 42 || mv.to == 35)));
+
+        // no check, the king is captured
+        let mut game = Game::with_variant_from_fen(Rc::new(duck_chess::DuckChess), "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1").unwrap();
+        let played = game.play(Move::new(4, 60).with_duck(0)).unwrap();
+        assert_eq!(played.san, "Rxe8,a1");
+        assert_eq!(played.game_over, Some(Outcome::win(Color::White, Termination::KingCaptured)));
+    }
+
+    #[test]
+    fn test_fairy_pieces() {
+
+        // the archbishop moves like a knight and a bishop, not like a rook
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/A3K3 w - - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&Move::new(0, 17)));
+        assert!(game.legal_moves().contains(&Move::new(0, 63)));
+        assert!(!game.legal_moves().contains(&Move::new(0, 56)));
+
+        // the camel jumps (1, 3)
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/L3K3 w - - 0 1").unwrap();
+        let camel: Vec<i8> = game.legal_moves().iter().filter(|mv| mv.from == 0).map(|mv| mv.to).collect();
+        assert_eq!(camel, vec![11, 25]);
+
+        // FEN letters, SAN, check and capture work like for the standard pieces
+        let fen = "z3k3/8/8/8/8/8/8/C3K3 w - - 0 1";
+        let mut game = Game::from_fen(fen).unwrap();
+        assert_eq!(game.fen(), fen);
+        assert_eq!(game.state().board.piece_at(56), Some((Color::Black, 'Z')));
+
+        let played = game.play(Move::new(0, 56)).unwrap();
+        assert_eq!(played.san, "Cxa8+");
+        assert!(perform_moves::is_check(game.state(), Color::White));
+        assert_eq!(game.fen(), "C3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        game.undo().unwrap();
+        assert_eq!(game.fen(), fen);
+    }
+
+    #[test]
+    fn test_seirawan() {
+
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[HEhe] w KQBCDFGkqbcdfg - 0 1";
+        let mut game = Game::with_variant(Rc::new(seirawan::Seirawan));
+        assert_eq!(game.fen(), start);
+        assert_eq!(Game::with_variant_from_fen(Rc::new(seirawan::Seirawan), start).unwrap().state().gating, game.state().gating);
+
+        // the knight's first move gates the hawk onto g1
+        let mv = notation::parse_variant_san(game.variant(), "Nf3/H", game.state()).unwrap();
+        assert_eq!(mv, Move::new(6, 21).with_gate('H', 6));
+        assert_eq!(game.play(mv).unwrap().san, "Nf3/H");
+        assert_eq!(game.fen(), "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKBHR[Ehe] b KQBCDFkqbcdfg - 1 1");
+
+        // moves from squares that already moved can't gate
+        game.play(Move::new(52, 36)).unwrap();
+        assert!(!game.legal_moves().iter().any(|mv| mv.gate.is_some() && mv.from == 21));
+
+        // castling gates onto the king's or the rook's square
+        let mut game = Game::with_variant_from_fen(Rc::new(seirawan::Seirawan), "r3k2r/8/8/8/8/8/8/R3K2R[HEhe] w KQkq - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&Move::new(4, 6).with_gate('E', 4)));
+        let played = game.play(notation::parse_variant_san(game.variant(), "O-O/Eh1", game.state()).unwrap()).unwrap();
+        assert_eq!(played.mv, Move::new(4, 6).with_gate('E', 7));
+        assert_eq!(game.fen(), "r3k2r/8/8/8/8/8/8/R4RKE[Hhe] b Akq - 1 1");
+        assert_eq!(fen::to_fen(&fen::from_fen(&game.fen()).unwrap()), game.fen());
+        assert_eq!(game.state().board.piece_at(7), Some((Color::White, 'E')));
+
+        // a pinned knight may move if the gated piece takes its place
+        let game = Game::with_variant_from_fen(Rc::new(seirawan::Seirawan), "4k3/8/8/8/8/8/8/r2NK3[H] w D - 0 1").unwrap();
+        assert!(!game.legal_moves().contains(&Move::new(3, 18)));
+        assert!(game.legal_moves().contains(&Move::new(3, 18).with_gate('H', 3)));
+
+        // pawns can promote to a hawk or an elephant
+        let mut game = Game::with_variant_from_fen(Rc::new(seirawan::Seirawan), "4k3/P7/8/8/8/8/8/4K3[] w - - 0 1").unwrap();
+        assert!(game.legal_moves().contains(&Move::with_promotion(48, 56, 'E')));
+        assert_eq!(game.play(Move::with_promotion(48, 56, 'H')).unwrap().san, "a8=H");
+        assert_eq!(game.state().board.piece_at(56), Some((Color::White, 'H')));
+        assert_eq!(game.state().board.white_pawns, 0);
+    }
+
+
+    #[test]
+    fn test_minichess() {
+
+        assert_eq!(variant::from_name("Los Alamos").unwrap().name(), "Los Alamos");
+
+        // 6x6: pawns move one square, knights stay on the board
+        let game = Game::with_variant(Rc::new(minichess::LosAlamos));
+        assert_eq!(game.fen(), minichess::LOS_ALAMOS_FEN);
+        assert_eq!(game.state().board.geometry, bitboards::Geometry::new(6, 6));
+        assert_eq!(game.legal_moves().len(), 10);
+        assert!(!game.legal_moves().contains(&Move::new(8, 24)));
+
+        // the rook and the king can't leave the board past the f-file and sixth rank
+        let game = Game::with_variant_from_fen(Rc::new(minichess::LosAlamos), "2k3/6/6/6/6/R4K w - - 0 1").unwrap();
+        assert_eq!(game.legal_moves().len(), 12);
+        assert!(game.legal_moves().iter().all(|mv| bitboards::Geometry::new(6, 6).squares() & 1<<mv.to != 0));
+
+        // promotion on the sixth rank, but not to a bishop
+        let game = Game::with_variant_from_fen(Rc::new(minichess::LosAlamos), "5k/P5/6/6/6/K5 w - - 0 1").unwrap();
+        let promotions: Vec<Option<char>> = game.legal_moves().iter().filter(|mv| mv.from == 32).map(|mv| mv.promotion).collect();
+        assert_eq!(promotions.len(), 3);
+        assert!(!promotions.contains(&Some('B')));
+
+        // 5x5
+        let game = Game::with_variant(Rc::new(minichess::Gardner));
+        assert_eq!(game.fen(), minichess::GARDNER_FEN);
+        assert_eq!(game.legal_moves().len(), 7);
+
+        let mut game = Game::with_variant_from_fen(Rc::new(minichess::Gardner), "4k/P4/5/5/K4 w - - 0 1").unwrap();
+        game.play(Move::with_promotion(24, 32, 'Q')).unwrap();
+        assert_eq!(game.fen(), "Q3k/5/5/5/K4 b - - 0 1");
+        assert!(perform_moves::is_check(game.state(), Color::White));
+
+        // black's back rank is the board's last rank, for castling rights too
+        let mut game = Game::from_fen("r2k2r/7/7/7/7/7/R2K2R w KQkq - 0 1").unwrap();
+        assert_eq!(game.fen(), "r2k2r/7/7/7/7/7/R2K2R w KQkq - 0 1");
+        game.play(Move::new(6, 54)).unwrap();
+        assert_eq!(game.fen(), "r2k2R/7/7/7/7/7/R2K3 b Qq - 0 1");
+
+        // a knight on the e-file edge only reaches the squares of the board
+        let state = fen::from_fen("k4/5/4N/5/K4 w - - 0 1").unwrap();
+        let knight = legal_moves::knight_moves(1<<20, &state, Color::White);
+        assert_eq!(knight, 1<<35 | 1<<26 | 1<<10 | 1<<3);
+        assert_eq!(legal_moves::king_pattern(1<<4, &state.board.geometry), 1<<3 | 1<<11 | 1<<12);
+        assert_eq!(legal_moves::pawn_attacks(1<<12, Color::White, &state.board.geometry), 1<<19);
+    }
+
+
+    #[test]
+    fn test_odds() {
+
+        use crate::odds::Odds;
+
+        // rook odds lose the castling right on that side
+        let state = GameState::new().with_odds(Color::White, Odds::Rook);
+        assert_eq!(fen::to_fen(&state), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w Kkq - 0 1");
+
+        let state = GameState::new().with_odds(Color::Black, Odds::Knight).with_odds(Color::Black, Odds::Queen);
+        assert_eq!(fen::to_fen(&state), "r1b1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+        // pawn and move: black starts, and the game is recorded from the handicap position
+        let mut game = Game::from_position(GameState::new().with_odds(Color::White, Odds::PawnAndMove));
+        assert_eq!(game.start_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1");
+        game.play(Move::new(52, 36)).unwrap();
+        game.resign(Color::White).unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]"));
+        assert!(pgn.contains("[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/RNBQKBNR b KQkq - 0 1\"]"));
+        assert!(pgn.contains("1... e5 0-1"));
+
+        // two moves in a row for white
+        let state = GameState::new().with_extra_moves(Color::White, &[Move::new(12, 28), Move::new(11, 27)]).unwrap();
+        assert_eq!(fen::to_fen(&state), "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR b KQkq d3 0 1");
+        assert!(GameState::new().with_extra_moves(Color::White, &[Move::new(12, 36)]).is_none());
+    }
+
+
+    #[test]
+    fn test_touch_move() {
+
+        let mut game = Game::new();
+        game.set_touch_move(true);
+
+        // adjusting doesn't commit, and a piece without legal moves doesn't either
+        assert_eq!(game.adjust(12), Ok(()));
+        assert_eq!(game.touch(12).unwrap().piece, None);
+        assert_eq!(game.adjust(52), Err(GameError::NoPiece(52)));
+        assert_eq!(game.touch(0).unwrap().piece, None);
+        assert_eq!(game.touch(20), Err(GameError::NoPiece(20)));
+
+        // the touched knight has to move
+        assert_eq!(game.touch(6).unwrap().piece, Some(6));
+        assert_eq!(game.touch(1).unwrap().piece, Some(6));
+        assert_eq!(game.play(Move::new(12, 28)), Err(GameError::TouchedPiece(6)));
+        game.play(Move::new(6, 21)).unwrap();
+        assert_eq!(game.touched(), game::Touched::default());
+
+        // an adjusted piece handled again still commits to nothing, another piece may move
+        game.adjust(57).unwrap();
+        assert_eq!(game.touch(57).unwrap().piece, None);
+        assert_eq!(game.touched().adjusted, 1<<57);
+
+        // a touched opponent's piece that can be captured has to be captured
+        game.play(Move::new(51, 35)).unwrap();
+        game.play(Move::new(12, 28)).unwrap();
+        game.play(Move::new(57, 42)).unwrap();
+        assert_eq!(game.touch(35).unwrap().target, Some(35));
+        assert_eq!(game.play(Move::new(11, 27)), Err(GameError::TouchedPiece(35)));
+
+        // touching the knight too: it can't capture on d5, so it only has to move
+        assert_eq!(game.touch(21).unwrap().piece, Some(21));
+        assert_eq!(game.play(Move::new(28, 35)), Err(GameError::TouchedPiece(21)));
+        game.play(Move::new(21, 38)).unwrap();
+
+        // without touch-move, touching commits to nothing
+        game.set_touch_move(false);
+        assert_eq!(game.touch(42), Ok(game::Touched::default()));
+        game.play(Move::new(52, 36)).unwrap();
+    }
+
+
+    #[test]
+    fn test_premoves() {
+
+        let mut game = Game::new();
+        game.play(Move::new(12, 28)).unwrap(); // e4
+
+        // white queues d4 and Nf3 while black thinks
+        assert_eq!(game.premove(Color::Black, Move::new(51, 35)), Err(GameError::OwnTurn));
+        assert_eq!(game.premove(Color::White, Move::new(0, 16)), Err(GameError::IllegalMove(Move::new(0, 16))));
+        game.premove(Color::White, Move::new(11, 27)).unwrap();
+        game.premove(Color::White, Move::new(6, 21)).unwrap();
+
+        // black's move triggers the first premove, the second waits for black's next move
+        let played = game.play(Move::new(51, 35)).unwrap(); // d5
+        match played.premove {
+            Some(game::Premove::Played(premove)) => assert_eq!(premove.san, "d4"),
+            other => panic!("premove not played: {:?}", other),
+        }
+        assert_eq!(game.premoves(), &[Move::new(6, 21)]);
+        assert_eq!(game.side_to_move(), Color::Black);
+
+        // exd5 isn't legal after dxe4, so it's cancelled together with the Nf3 behind it
+        game.cancel_premoves();
+        game.premove(Color::White, Move::new(28, 35)).unwrap();
+        game.premove(Color::White, Move::new(6, 21)).unwrap();
+        let played = game.play(Move::new(35, 28)).unwrap(); // dxe4
+        assert_eq!(played.premove, Some(game::Premove::Cancelled(Move::new(28, 35))));
+        assert!(game.premoves().is_empty());
+        assert_eq!(game.side_to_move(), Color::White);
+    }
+
+
+    #[test]
+    fn test_takebacks() {
+
+        let time = ManualClock::new();
+        let secs = Duration::from_secs;
+
+        let mut game = Game::new();
+        game.set_clock(ChessClock::new(TimeControl::sudden_death(secs(60), Timing::NoIncrement), Box::new(time.clone())));
+
+        time.advance(secs(5));
+        game.play(Move::new(12, 28)).unwrap(); // e4
+        time.advance(secs(10));
+        game.play(Move::new(52, 36)).unwrap(); // e5
+        time.advance(secs(3));
+        game.play(Move::new(6, 21)).unwrap(); // Nf3
+
+        // black takes back e5 (and white's reply), the clock goes back to the start of black's turn
+        assert_eq!(game.request_takeback(Color::Black, 3), Err(GameError::TakebackNotAllowed(3)));
+        game.request_takeback(Color::Black, 2).unwrap();
+        assert_eq!(game.accept_takeback(Color::Black), Err(GameError::NoTakebackRequest));
+        assert_eq!(game.accept_takeback(Color::White), Ok(vec![Move::new(52, 36), Move::new(6, 21)]));
+        assert_eq!(game.fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        let clock = game.clock().unwrap();
+        assert_eq!((clock.remaining(Color::White), clock.remaining(Color::Black)), (secs(55), secs(60)));
+        assert_eq!(clock.running(), Some(Color::Black));
+
+        // declined requests are recorded too
+        game.request_takeback(Color::White, 1).unwrap();
+        game.decline_takeback(Color::Black).unwrap();
+        game.play(Move::new(51, 35)).unwrap(); // d5
+
+        assert_eq!(game.takebacks().len(), 2);
+        assert!(game.takebacks()[0].accepted && !game.takebacks()[1].accepted);
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("1. e4 {Takeback by Black: e5 Nf3 (accepted), Takeback by White: e4 (declined)}"));
+        assert!(pgn.contains("1... d5 *"));
+
+        // a plain undo also gives white back the time spent on exd5
+        time.advance(secs(4));
+        game.play(Move::new(28, 35)).unwrap(); // exd5
+        game.undo().unwrap();
+        let clock = game.clock().unwrap();
+        assert_eq!((clock.remaining(Color::White), clock.remaining(Color::Black)), (secs(55), secs(60)));
+        assert_eq!(clock.running(), Some(Color::White));
+    }
+
+
+    #[test]
+    fn test_adjudication() {
+
+        use crate::adjudication::{AdjudicationRules, Adjudicator, Tablebase, Wdl};
+
+        let rules = AdjudicationRules {
+            draw_from_move: 2,
+            draw_moves: 2,
+            draw_threshold: 20,
+            resign_moves: 2,
+            resign_threshold: 500,
+            tablebase_pieces: 3,
+        };
+
+        // a draw once 2 moves (4 evaluations) in a row from move 2 on are within 0.20
+        let mut game = Game::new();
+        let mut adjudicator = Adjudicator::new(rules);
+        for (mv, eval) in [(Move::new(12, 28), 15), (Move::new(52, 36), -10), (Move::new(6, 21), 5), (Move::new(57, 42), 0), (Move::new(5, 26), 10)] {
+            game.play(mv).unwrap();
+            adjudicator.add_eval(game.state(), eval);
+            assert_eq!(game.adjudicate(&adjudicator), None);
+        }
+        game.play(Move::new(61, 34)).unwrap();
+        adjudicator.add_eval(game.state(), -5);
+        assert_eq!(game.adjudicate(&adjudicator), Some(Outcome::draw(Termination::AdjudicationDraw)));
+        assert!(game.to_pgn().contains("[Termination \"adjudication\"]"));
+
+        // a win once 2 moves (4 evaluations) in a row are 5.00 or more for black
+        let mut game = Game::new();
+        let mut adjudicator = Adjudicator::new(rules);
+        for (mv, eval) in [(Move::new(12, 28), 100), (Move::new(52, 36), -700), (Move::new(6, 21), -800)] {
+            game.play(mv).unwrap();
+            adjudicator.add_eval(game.state(), eval);
+        }
+        assert_eq!(game.adjudicate(&adjudicator), None);
+
+        // evaluations of moves taken back don't count any more
+        game.request_takeback(Color::Black, 2).unwrap();
+        game.accept_takeback(Color::White).unwrap();
+        game.play(Move::new(51, 35)).unwrap();
+        adjudicator.add_eval(game.state(), -900);
+        assert_eq!(game.adjudicate(&adjudicator), None);
+        for (mv, eval) in [(Move::new(28, 35), -1000), (Move::new(59, 35), -1100)] {
+            game.play(mv).unwrap();
+            adjudicator.add_eval(game.state(), eval);
+        }
+        assert_eq!(game.adjudicate(&adjudicator), None);
+        game.play(Move::new(1, 18)).unwrap();
+        adjudicator.add_eval(game.state(), -1200);
+        assert_eq!(game.adjudicate(&adjudicator), Some(Outcome::win(Color::Black, Termination::AdjudicationWin)));
+
+        // extreme evaluations don't overflow
+        let mut game = Game::new();
+        let mut adjudicator = Adjudicator::new(AdjudicationRules { draw_from_move: 1, draw_moves: 1, resign_moves: 0, ..rules });
+        game.play(Move::new(12, 28)).unwrap();
+        adjudicator.add_eval(game.state(), i32::MIN);
+        game.play(Move::new(52, 36)).unwrap();
+        adjudicator.add_eval(game.state(), i32::MIN);
+        assert_eq!(game.adjudicate(&adjudicator), None);
+
+        // few pieces: the tablebase decides, a cursed win is a draw
+        struct RookEnding;
+        impl Tablebase for RookEnding {
+            fn probe_wdl(&self, state: &GameState) -> Option<Wdl> {
+                match state.side_to_move {
+                    Color::White => Some(Wdl::Win),
+                    Color::Black => Some(Wdl::BlessedLoss),
+                }
+            }
+        }
+        let adjudicator = Adjudicator::new(rules).with_tablebase(Box::new(RookEnding));
+        let mut game = Game::from_fen("8/8/8/4k3/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(game.adjudicate(&adjudicator), Some(Outcome::win(Color::White, Termination::TablebaseAdjudication)));
+        assert_eq!(game.undo(), Err(GameError::GameOver));
+        let state = fen::from_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 1").unwrap();
+        assert_eq!(adjudicator.verdict(&state, &variant::Standard), Some(Outcome::draw(Termination::TablebaseAdjudication)));
+        assert_eq!(adjudicator.verdict(&GameState::new(), &variant::Standard), None);
+
+        // not for other variants, or while castling is possible
+        assert_eq!(adjudicator.verdict(&state, &crazyhouse::Crazyhouse), None);
+        let state = fen::from_fen("8/8/8/4k3/8/8/8/R3K3 b Q - 0 1").unwrap();
+        assert_eq!(adjudicator.verdict(&state, &variant::Standard), None);
+    }
+
+
+    #[test]
+    fn test_bughouse_premove() {
+
+        use crate::bughouse::{BughouseGame, BOARD_A, BOARD_B};
+        use crate::game::Premove;
+
+        let mut game = BughouseGame::new();
+        for mv in [Move::new(12, 28), Move::new(51, 35), Move::new(1, 18)] {
+            game.play(BOARD_A, mv).unwrap();
+        }
+
+        // the pawn taken by white's premove goes to the partner on board B like any capture
+        game.premove(BOARD_A, Color::White, Move::new(28, 35)).unwrap();
+        let played = game.play(BOARD_A, Move::new(62, 45)).unwrap();
+        assert!(matches!(played.premove, Some(Premove::Played(_))));
+        assert_eq!(game.board(BOARD_B).state().pockets.unwrap().black.pawns, 1);
+        assert!(game.to_bpgn().contains("2A. Nc3 2a. Nf6 3A. exd5 *"));
+    }
 }
@@ -0,0 +1,47 @@
+/*
+Minichess on the smaller boards in the a1 corner (bitboards::Geometry), with the standard rules and
+move generation otherwise:
+
+- Los Alamos chess: 6x6 without bishops, pawns promote to a queen, rook or knight.
+- Gardner minichess: 5x5 with one of each piece.
+
+On both, pawns only move one square at a time (so there's no en passant) and there's no castling.
+*/
+
+use crate::perform_moves::Move;
+use crate::state::GameState;
+use crate::variant::Variant;
+
+pub const LOS_ALAMOS_FEN: &str = "rnqknr/pppppp/6/6/PPPPPP/RNQKNR w - - 0 1";
+pub const GARDNER_FEN: &str = "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1";
+
+pub struct LosAlamos;
+
+impl Variant for LosAlamos {
+
+    fn name(&self) -> &'static str {
+        "Los Alamos"
+    }
+
+    fn start_position(&self) -> GameState {
+        crate::fen::from_fen(LOS_ALAMOS_FEN).unwrap()
+    }
+
+    // there are no bishops to promote to
+    fn filter_moves(&self, _state: &GameState, moves: Vec<Move>) -> Vec<Move> {
+        moves.into_iter().filter(|mv| mv.promotion != Some('B')).collect()
+    }
+}
+
+pub struct Gardner;
+
+impl Variant for Gardner {
+
+    fn name(&self) -> &'static str {
+        "Gardner"
+    }
+
+    fn start_position(&self) -> GameState {
+        crate::fen::from_fen(GARDNER_FEN).unwrap()
+    }
+}
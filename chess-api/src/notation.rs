@@ -0,0 +1,196 @@
+/*
+Converting squares and moves to and from text.
+
+- Squares are written as file + rank, eg. square 0 = "a1", square 63 = "h8" (LERF mapping).
+- Moves are written in Standard Algebraic Notation (SAN), eg. "e4", "Nxf7+", "exd6", "O-O", "e8=Q#",
+  and drops (Crazyhouse) as piece @ square, eg. "N@f3", "P@e6" ("@e6" is also read).
+
+SAN needs to know the position the move is made in, both to find the moving piece and to
+disambiguate between two pieces of the same type that can reach the same square.
+*/
+
+use crate::outcome::Termination;
+use crate::perform_moves::{is_castling_move, Move};
+use crate::state::GameState;
+use crate::variant::{legal_moves, next_state, outcome, Standard, Variant};
+
+pub fn square_name(square: i8) -> String {
+
+    let file = (b'a' + (square % 8) as u8) as char;
+    let rank = (b'1' + (square / 8) as u8) as char;
+
+    format!("{}{}", file, rank)
+}
+
+pub fn parse_square(text: &str) -> Option<i8> {
+
+    let bytes = text.as_bytes();
+
+    if bytes.len() != 2 {
+        return None;
+    }
+
+    let file = bytes[0].to_ascii_lowercase();
+    let rank = bytes[1];
+
+    if !(b'a'..=b'h').contains(&file) || !(b'1'..=b'8').contains(&rank) {
+        return None;
+    }
+
+    Some(((rank - b'1') * 8 + (file - b'a')) as i8)
+}
+
+pub fn to_san(mv: &Move, state: &GameState) -> String {
+
+    /*
+    SAN of mv in state, including "+" for check and "#" for checkmate.
+    mv is assumed to be legal in state.
+    */
+
+    to_variant_san(&Standard, mv, state)
+}
+
+pub fn to_variant_san(variant: &dyn Variant, mv: &Move, state: &GameState) -> String {
+
+    // to_san() with the rules of `variant` deciding which moves are legal and what is checkmate.
+
+    let legal_moves = legal_moves(variant, state);
+    let mut san = san_without_suffix(mv, state, &legal_moves);
+
+    let next = next_state(variant, state, mv);
+
+    if variant.in_check(&next, next.side_to_move) {
+
+        let mated = outcome(variant, &next).map(|outcome| outcome.termination) == Some(Termination::Checkmate);
+        san.push(if mated { '#' } else { '+' });
+    }
+
+    san
+}
+
+pub fn parse_san(text: &str, state: &GameState) -> Option<Move> {
+
+    /*
+    Finds the legal move in state matching the SAN text. Check/mate markers, annotations ("!", "?")
+    and the "=" in promotions are optional, castling can be written with zeros ("0-0").
+    */
+
+    parse_variant_san(&Standard, text, state)
+}
+
+pub fn parse_variant_san(variant: &dyn Variant, text: &str, state: &GameState) -> Option<Move> {
+
+    let wanted = normalize_san(text);
+
+    if wanted.is_empty() {
+        return None;
+    }
+
+    let legal_moves = legal_moves(variant, state);
+
+    legal_moves.iter()
+        .find(|mv| normalize_san(&san_without_suffix(mv, state, &legal_moves)) == wanted)
+        .copied()
+}
+
+fn normalize_san(text: &str) -> String {
+
+    let san: String = text.trim()
+        .replace('0', "O")
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '='))
+        .collect();
+
+    // a pawn drop may leave out the P
+    if san.starts_with('@') {
+        return format!("P{}", san);
+    }
+    san
+}
+
+fn san_without_suffix(mv: &Move, state: &GameState, legal_moves: &[Move]) -> String {
+
+    let board = &state.board;
+
+    // Duck chess: the move, then the duck's square, eg. "Nf3,d5"
+    if let Some(duck) = mv.duck {
+        let piece_move = Move { duck: None, ..*mv };
+        return format!("{},{}", san_without_suffix(&piece_move, state, legal_moves), square_name(duck));
+    }
+
+    // S-Chess: the move, then the gated piece, with its square when castling, eg. "Nf3/H", "O-O/Eh1"
+    if let Some((piece, square)) = mv.gate {
+        let piece_move = Move { gate: None, ..*mv };
+        let mut san = format!("{}/{}", san_without_suffix(&piece_move, state, legal_moves), piece.to_ascii_uppercase());
+        if is_castling_move(mv.from, mv.to, state) {
+            san.push_str(&square_name(square));
+        }
+        return san;
+    }
+
+    if let Some(piece) = mv.drop {
+        return format!("{}@{}", piece.to_ascii_uppercase(), square_name(mv.to));
+    }
+
+    let piece = match board.piece_at(mv.from) {
+        Some((_, piece)) => piece,
+        None => return String::new(),
+    };
+
+    let target_mask: u64 = 1<<mv.to;
+    let is_capture = board.piece_at(mv.to).is_some()
+        || (piece == 'P' && target_mask == state.en_passant_mask);
+
+    // castling, kingside when the king moves towards the h-file
+    if is_castling_move(mv.from, mv.to, state) {
+        return if mv.to > mv.from { "O-O".to_string() } else { "O-O-O".to_string() };
+    }
+
+    let mut san = String::new();
+
+    if piece == 'P' {
+
+        if is_capture {
+            san.push((b'a' + (mv.from % 8) as u8) as char);
+            san.push('x');
+        }
+        san.push_str(&square_name(mv.to));
+
+        if let Some(promotion) = mv.promotion {
+            san.push('=');
+            san.push(promotion.to_ascii_uppercase());
+        }
+
+        return san;
+    }
+
+    san.push(piece);
+
+    // other pieces of the same type that can move to the same square
+    let ambiguous: Vec<&Move> = legal_moves.iter()
+        .filter(|other| other.to == mv.to && other.from != mv.from)
+        .filter(|other| board.piece_at(other.from).map(|(_, p)| p) == Some(piece))
+        .collect();
+
+    if !ambiguous.is_empty() {
+
+        let same_file = ambiguous.iter().any(|other| other.from % 8 == mv.from % 8);
+        let same_rank = ambiguous.iter().any(|other| other.from / 8 == mv.from / 8);
+        let from_name = square_name(mv.from);
+
+        if !same_file {
+            san.push_str(&from_name[..1]);
+        } else if !same_rank {
+            san.push_str(&from_name[1..]);
+        } else {
+            san.push_str(&from_name);
+        }
+    }
+
+    if is_capture {
+        san.push('x');
+    }
+    san.push_str(&square_name(mv.to));
+
+    san
+}
@@ -0,0 +1,96 @@
+/*
+Handicap (odds) games: the stronger player gives up material or tempo at the start.
+
+- Odds::Knight, Rook, Queen: the giver starts without that piece, the queenside one if there are
+  two (b1/b8 knight, a1/a8 rook). Removing a rook loses the castling right on that side.
+- Odds::Pawn: the giver starts without the f-pawn.
+- Odds::PawnAndMove: the f-pawn, and the receiving side moves first.
+- GameState::with_extra_moves(): the receiving side plays several moves in a row before the giver's
+  first move.
+
+The odds only change the start position: Game::from_position() records it in the PGN SetUp/FEN tags,
+and the game is scored like any other, by color.
+*/
+
+use crate::perform_moves::{empty_square, is_check, update_castling_rights, Move};
+use crate::state::{Color, GameState};
+use crate::variant::{legal_moves, next_state, Standard};
+
+const FILE_F: u64 = 0x2020202020202020;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Odds {
+    Pawn,
+    PawnAndMove,
+    Knight,
+    Rook,
+    Queen,
+}
+
+impl GameState {
+
+    // The position with giver's odds removed, eg. GameState::new().with_odds(Color::White, Odds::Rook).
+    // Odds can be combined by calling this again. If giver doesn't have the piece anymore, nothing
+    // is removed.
+    pub fn with_odds(mut self, giver: Color, odds: Odds) -> Self {
+
+        let board = &self.board;
+
+        let (back_rank, pawn_rank): (u64, u64) = match giver {
+            Color::White => (0xFF, 0xFF << 8),
+            Color::Black => (0xFF << 56, 0xFF << 48),
+        };
+
+        let pieces = match (giver, odds) {
+            (Color::White, Odds::Pawn | Odds::PawnAndMove) => board.white_pawns & pawn_rank & FILE_F,
+            (Color::Black, Odds::Pawn | Odds::PawnAndMove) => board.black_pawns & pawn_rank & FILE_F,
+            (Color::White, Odds::Knight) => board.white_knights & back_rank,
+            (Color::Black, Odds::Knight) => board.black_knights & back_rank,
+            (Color::White, Odds::Rook) => board.white_rooks & back_rank,
+            (Color::Black, Odds::Rook) => board.black_rooks & back_rank,
+            (Color::White, Odds::Queen) => board.white_queens & back_rank,
+            (Color::Black, Odds::Queen) => board.black_queens & back_rank,
+        };
+
+        // the queenside piece has the lowest square
+        if pieces != 0 {
+            let square = pieces.trailing_zeros() as i8;
+            empty_square(1<<square, &mut self.board);
+            update_castling_rights(square, &mut self);
+        }
+
+        if odds == Odds::PawnAndMove {
+            self.side_to_move = giver.opposite();
+        }
+
+        self
+    }
+
+    // The position after receiver plays moves in a row, with the other side to move afterwards.
+    // None if a move isn't legal, or gives check before the last one.
+    pub fn with_extra_moves(mut self, receiver: Color, moves: &[Move]) -> Option<Self> {
+
+        let fullmove_number = self.fullmove_number;
+
+        for (i, mv) in moves.iter().enumerate() {
+
+            self.side_to_move = receiver;
+
+            if !legal_moves(&Standard, &self).contains(mv) {
+                return None;
+            }
+            self = next_state(&Standard, &self, mv);
+
+            if i + 1 < moves.len() {
+                if is_check(&self, receiver) {
+                    return None;
+                }
+                self.fullmove_number = fullmove_number;
+            }
+        }
+
+        self.side_to_move = receiver.opposite();
+
+        Some(self)
+    }
+}
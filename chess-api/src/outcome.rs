@@ -0,0 +1,158 @@
+/*
+How a game ended: the result (who won) and the reason (termination), plus the material rules
+used to decide draws.
+*/
+
+use crate::bitboards::Board;
+use crate::state::Color;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+
+    pub fn win_for(color: Color) -> GameResult {
+        match color {
+            Color::White => GameResult::WhiteWins,
+            Color::Black => GameResult::BlackWins,
+        }
+    }
+
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameResult::WhiteWins => Some(Color::White),
+            GameResult::BlackWins => Some(Color::Black),
+            GameResult::Draw => None,
+        }
+    }
+
+    // The result as written in PGN, eg. "1-0".
+    pub fn as_pgn(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    Checkmate,
+    Stalemate, // a draw, except in Antichess and Duck chess where the stalemated player wins
+    Resignation,
+    DrawAgreement,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    TimeForfeit,
+    TimeoutVsInsufficientMaterial, // flag fell, but the opponent can't checkmate: draw
+    KingInCentre, // King of the Hill
+    ThreeChecks, // Three-check
+    KingExploded, // Atomic
+    AllPiecesLost, // a player has no pieces left, which wins in Antichess and loses in Horde
+    KingReachedGoal, // Racing Kings, a draw if both kings got there
+    KingCaptured, // Fog of war, Duck chess
+    AdjudicationDraw, // evaluations close to 0 for long enough (adjudication module)
+    AdjudicationWin, // evaluations decisive for long enough
+    TablebaseAdjudication, // settled by an endgame tablebase
+}
+
+impl Termination {
+
+    // Whether the game ended by the position on the board (so taking back a move re-opens it),
+    // rather than by a player's decision.
+    pub fn is_on_board(self) -> bool {
+        matches!(self,
+            Termination::Checkmate | Termination::Stalemate | Termination::FivefoldRepetition
+            | Termination::SeventyFiveMoveRule | Termination::InsufficientMaterial
+            | Termination::KingInCentre | Termination::ThreeChecks | Termination::KingExploded
+            | Termination::AllPiecesLost | Termination::KingReachedGoal | Termination::KingCaptured)
+    }
+
+    // The PGN Termination tag.
+    pub fn as_pgn(self) -> &'static str {
+        match self {
+            Termination::TimeForfeit | Termination::TimeoutVsInsufficientMaterial => "time forfeit",
+            Termination::AdjudicationDraw | Termination::AdjudicationWin
+            | Termination::TablebaseAdjudication => "adjudication",
+            _ => "normal",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub result: GameResult,
+    pub termination: Termination,
+}
+
+impl Outcome {
+
+    pub fn win(winner: Color, termination: Termination) -> Outcome {
+        Outcome { result: GameResult::win_for(winner), termination }
+    }
+
+    pub fn draw(termination: Termination) -> Outcome {
+        Outcome { result: GameResult::Draw, termination }
+    }
+}
+
+
+// MATERIAL
+
+const LIGHT_SQUARES: u64 = 0x55AA55AA55AA55AA;
+
+pub fn can_checkmate(board: &Board, side: Color) -> bool {
+
+    /*
+    Whether `side` has enough material to ever checkmate, if the opponent helps (used to decide
+    whether running out of time loses or draws).
+    A lone king can't. A lone knight only mates if the opponent has a piece that can block its own
+    king. Bishops that are all on squares of one color only mate if the opponent has a blocker
+    other than bishops on that same color.
+    */
+
+    let (pawns, knights, bishops, rooks, queens) = match side {
+        Color::White => (board.white_pawns, board.white_knights, board.white_bishops, board.white_rooks, board.white_queens),
+        Color::Black => (board.black_pawns, board.black_knights, board.black_bishops, board.black_rooks, board.black_queens),
+    };
+
+    let (opponent_pieces, opponent_bishops) = match side {
+        Color::White => (board.black_occupied & !board.black_king, board.black_bishops),
+        Color::Black => (board.white_occupied & !board.white_king, board.white_bishops),
+    };
+
+    // fairy pieces are assumed to be able to mate
+    if pawns | rooks | queens | board.fairy_pieces(side) != 0 {
+        return true;
+    }
+
+    if knights | bishops == 0 {
+        return false;
+    }
+
+    if bishops == 0 && knights.count_ones() == 1 {
+        return opponent_pieces != 0;
+    }
+
+    if knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0) {
+        let same_colored = if bishops & LIGHT_SQUARES != 0 { LIGHT_SQUARES } else { !LIGHT_SQUARES };
+        return opponent_pieces & !(opponent_bishops & same_colored) != 0;
+    }
+
+    true
+}
+
+pub fn is_insufficient_material(board: &Board) -> bool {
+
+    // Neither side can checkmate: a dead position, eg. king vs king, king + minor piece vs king
+    // or only bishops on squares of the same color.
+    !can_checkmate(board, Color::White) && !can_checkmate(board, Color::Black)
+}
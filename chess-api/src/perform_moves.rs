@@ -1,5 +1,6 @@
 
 use crate::legal_moves::bishop_moves;
+use crate::legal_moves::castling_squares;
 use crate::legal_moves::king_moves;
 use crate::legal_moves::knight_moves;
 use crate::legal_moves::pawn_moves;
@@ -7,18 +8,17 @@ use crate::legal_moves::queen_moves;
 use crate::legal_moves::rook_moves;
 use crate::state::GameState;
 use crate::bitboards::Board;
+use crate::fairy::{fairy_moves, FAIRY_COUNT};
 use crate::state::Color;
 use crate::state::History;
 use std::io::{self, Write}; // take input, got some help from Arvid Kristofferson on how to take input in rust
 
-const SECOND_RANK: u64 = 0b1111111100000000; 
-const SEVENTH_RANK: u64 = 0xFF000000000000;
-const FIRST_RANK: u64 = 0x00000000000000FF;
-const EIGHT_RANK: u64 = 0xFF00000000000000;
+
+pub const PROMOTION_PIECES: [char; 4] = ['Q', 'R', 'B', 'N'];
 
 pub fn is_legal(cur_square: i8, target_square: i8, state: &GameState) -> bool {
 
-    if cur_square < 0 || cur_square > 63 || target_square < 0 || target_square > 63 {
+    if !(0..=63).contains(&cur_square) || !(0..=63).contains(&target_square) {
         return false;
     }
 
@@ -37,58 +37,70 @@ pub fn is_legal(cur_square: i8, target_square: i8, state: &GameState) -> bool {
 
 
     if ((cur_mask & board.white_pawns) != 0) && white_to_move {
-        legal_piece_movement = (pawn_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (pawn_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.white_bishops) != 0) && white_to_move {
-        legal_piece_movement = (bishop_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (bishop_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.white_knights) != 0) && white_to_move {
-        legal_piece_movement = (knight_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (knight_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.white_rooks) != 0) && white_to_move {
-        legal_piece_movement = (rook_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (rook_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.white_queens) != 0) && white_to_move {
-        legal_piece_movement = (queen_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (queen_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.white_king) != 0) && white_to_move {
-        legal_piece_movement = (king_moves(cur_mask, &state, side,true) & target_mask) != 0;
+        legal_piece_movement = (king_moves(cur_mask, state, side,true) & target_mask) != 0;
     }
  
     if ((cur_mask & board.black_pawns) != 0) && !white_to_move {
-        legal_piece_movement = (pawn_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (pawn_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.black_bishops) != 0) && !white_to_move {
-        legal_piece_movement = (bishop_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (bishop_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.black_knights) != 0) && !white_to_move {
-        legal_piece_movement = (knight_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (knight_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.black_rooks) != 0) && !white_to_move {
-        legal_piece_movement = (rook_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (rook_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.black_queens) != 0) && !white_to_move {
-        legal_piece_movement = (queen_moves(cur_mask, &state, side) & target_mask) != 0;
+        legal_piece_movement = (queen_moves(cur_mask, state, side) & target_mask) != 0;
     }
 
     if ((cur_mask & board.black_king) != 0) && !white_to_move {
-        legal_piece_movement = (king_moves(cur_mask, &state, side, true) & target_mask) != 0;
+        legal_piece_movement = (king_moves(cur_mask, state, side, true) & target_mask) != 0;
+    }
+
+    for index in 0..FAIRY_COUNT {
+
+        let fairy = match side {
+            Color::White => board.white_fairy[index],
+            Color::Black => board.black_fairy[index],
+        };
+
+        if (cur_mask & fairy) != 0 {
+            legal_piece_movement = (fairy_moves(cur_mask, index, state, side) & target_mask) != 0;
+        }
     }
 
     if !legal_piece_movement {
         return false;
     }
 
-    return true;
+    true
 }
 
 
@@ -96,7 +108,7 @@ pub fn read_cur_square(square: i8, state: &GameState) -> bool {
 
     let board = &state.board;
 
-    if square < 0 || square > 63 {
+    if !(0..=63).contains(&square) {
         return false;
     }
 
@@ -105,14 +117,16 @@ pub fn read_cur_square(square: i8, state: &GameState) -> bool {
     match state.side_to_move {
         Color::White => {
             if cur_mask & (board.white_pawns | board.white_knights | board.white_bishops
-                       | board.white_rooks | board.white_queens | board.white_king) == 0
+                       | board.white_rooks | board.white_queens | board.white_king
+                       | board.fairy_pieces(Color::White)) == 0
             {
                 return false;
             }
         }
         Color::Black => {
             if cur_mask & (board.black_pawns | board.black_knights | board.black_bishops
-                       | board.black_rooks | board.black_queens | board.black_king) == 0
+                       | board.black_rooks | board.black_queens | board.black_king
+                       | board.fairy_pieces(Color::Black)) == 0
             {
                 return false;
             } 
@@ -122,46 +136,83 @@ pub fn read_cur_square(square: i8, state: &GameState) -> bool {
     true
 }
 
-pub fn make_move(cur_square: i8, target_square: i8, state: &mut GameState, history: &mut History, stop_reset: bool) -> bool{
-
-    // stop_reset is set to true during testing
-    let cur_mask: u64 = 1<<cur_square;
-    let target_mask: u64 = 1<<target_square;
-
+/*
+A move from one square to another. `promotion` is the piece a pawn promotes to ('Q', 'R', 'B'
+or 'N'). If it's None when a pawn reaches the last rank, the player is asked for it.
+
+In variants with pieces in hand (Crazyhouse) a move can instead drop the piece `drop` from the
+pocket onto the empty square `to`. For drops `from` is the same as `to`.
+
+In Duck chess a move has a second part: after moving, the player puts the duck on the empty
+square `duck`.
+
+In S-Chess `gate` is the reserve piece ('H' or 'E') put on the square the move vacated, the king's
+or the rook's square when castling.
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub from: i8,
+    pub to: i8,
+    pub promotion: Option<char>,
+    pub drop: Option<char>,
+    pub duck: Option<i8>,
+    pub gate: Option<(char, i8)>,
+}
 
-    // is_legal checks if the player has a piece on cur_square that can be moved to target_square
-    // is_legal does NOT account for self-check
-    if !is_legal(cur_square, target_square, &state) {
-        print!("{}", "Invalid move.\n");
-        return false;
+impl Move {
+    pub fn new(from: i8, to: i8) -> Self {
+        Self { from, to, promotion: None, drop: None, duck: None, gate: None }
     }
 
-    // save current state to history
-    history.push(state.clone());
+    pub fn with_promotion(from: i8, to: i8, piece: char) -> Self {
+        Self { from, to, promotion: Some(piece), drop: None, duck: None, gate: None }
+    }
 
+    pub fn new_drop(piece: char, to: i8) -> Self {
+        Self { from: to, to, promotion: None, drop: Some(piece), duck: None, gate: None }
+    }
 
+    // This move, followed by putting the duck on `square`.
+    pub fn with_duck(self, square: i8) -> Self {
+        Self { duck: Some(square), ..self }
+    }
 
-    let board = &mut state.board;
+    // This move, gating piece onto `square` (S-Chess).
+    pub fn with_gate(self, piece: char, square: i8) -> Self {
+        Self { gate: Some((piece, square)), ..self }
+    }
 
+    // Piece letters in uppercase, as generated by the move generators.
+    pub fn normalized(self) -> Self {
+        Self {
+            promotion: self.promotion.map(|piece| piece.to_ascii_uppercase()),
+            drop: self.drop.map(|piece| piece.to_ascii_uppercase()),
+            gate: self.gate.map(|(piece, square)| (piece.to_ascii_uppercase(), square)),
+            ..self
+        }
+    }
+}
 
-    // if capture
-    // remove piece from opponent's piece
-    capture(target_mask, board);
+pub fn make_move(cur_square: i8, target_square: i8, state: &mut GameState, history: &mut History, stop_reset: bool) -> bool{
 
-    // add piece to target square
-    fill_square(cur_mask, target_mask, board);
+    make_move_with_promotion(cur_square, target_square, None, state, history, stop_reset)
+}
 
-     // remove piece from current square
-    empty_square(cur_mask, board);
+pub fn make_move_with_promotion(cur_square: i8, target_square: i8, promotion_piece: Option<char>, state: &mut GameState, history: &mut History, stop_reset: bool) -> bool{
 
+    // stop_reset is set to true during testing
 
-    en_passant(target_mask, state);
-    update_en_passant_square(cur_square, target_square, state);
+    // is_legal checks if the player has a piece on cur_square that can be moved to target_square
+    // is_legal does NOT account for self-check
+    if !is_legal(cur_square, target_square, state) {
+        println!("Invalid move.");
+        return false;
+    }
 
-    castle(cur_square, target_square, state);
-    update_castling_rights(cur_square, state);
+    // save current state, it goes into history once we know the move doesn't leave us in check
+    let previous = state.clone();
 
-    promotion(target_square, state);
+    apply_move(cur_square, target_square, promotion_piece, state);
 
 
     // toggle turn temporarily
@@ -172,15 +223,15 @@ pub fn make_move(cur_square: i8, target_square: i8, state: &mut GameState, histo
     // Check self-check
     if is_check(state, opponent) {
 
-        print!("SELFCHECK\n");
-        // Undo move using history
-        if let Some(prev) = history.pop() {
-            *state = prev;
-        }
+        println!("SELFCHECK");
+        // Undo move (history is left untouched, so the redo stack survives an illegal attempt)
+        *state = previous;
 
         return false;
     }
 
+    history.push(previous);
+
     // toggle turns
     state.side_to_move = state.side_to_move.opposite(); 
 
@@ -191,9 +242,9 @@ pub fn make_move(cur_square: i8, target_square: i8, state: &mut GameState, histo
     // If not, it's either checkmate or stalemate.
     if is_checkmate_stalemate(state) {
         if is_check(state, state.side_to_move.opposite()){
-            print!("CHECKMATE\n");
+            println!("CHECKMATE");
         } else {
-            print!("STALEMATE\n");
+            println!("STALEMATE");
         }
 
         if !stop_reset {
@@ -203,15 +254,206 @@ pub fn make_move(cur_square: i8, target_square: i8, state: &mut GameState, histo
     }
 
 
-    return true;
+    true
 
 }
 
+pub fn apply_move(cur_square: i8, target_square: i8, promotion_piece: Option<char>, state: &mut GameState) {
+
+    /*
+    Updates the board for a move that is_legal() accepted: captures, en passant, castling and
+    promotion. Doesn't check for self-check and doesn't toggle state.side_to_move.
+    */
+
+    let cur_mask: u64 = 1<<cur_square;
+    let target_mask: u64 = 1<<target_square;
+
+    // half-move clock (fifty-move rule) is reset by pawn moves and captures
+    let pawns = state.board.white_pawns | state.board.black_pawns;
+    let occupied = state.board.white_occupied | state.board.black_occupied;
+
+    if (cur_mask & pawns) != 0 || (target_mask & occupied) != 0 {
+        state.halfmove_clock = 0;
+    } else {
+        state.halfmove_clock += 1;
+    }
+
+    if state.side_to_move == Color::Black {
+        state.fullmove_number += 1;
+    }
+
+    // castling moves the king and the rook together (in Chess960 the king's target square is
+    // its own rook's square, so it must not be handled as a capture)
+    if is_castling_move(cur_square, target_square, state) {
+        castle(cur_square, target_square, state);
+        update_castling_rights(cur_square, state);
+        state.en_passant_mask = 0;
+        return;
+    }
+
+    let board = &mut state.board;
+
+
+    // if capture
+    // remove piece from opponent's piece
+    capture(target_mask, board);
+
+    // add piece to target square
+    fill_square(cur_mask, target_mask, board);
+
+     // remove piece from current square
+    empty_square(cur_mask, board);
+
+
+    en_passant(target_mask, state);
+    update_en_passant_square(cur_square, target_square, state);
+
+    update_castling_rights(cur_square, state);
+    update_castling_rights(target_square, state); // a captured rook can't castle anymore
+
+    match promotion_piece {
+        Some(piece) => { promote(target_square, piece, state); }
+        None => promotion(target_square, state),
+    }
+}
+
+pub fn apply_drop(piece: char, square: i8, state: &mut GameState) -> bool {
+
+    /*
+    Takes piece out of the side to move's pocket and puts it on square. Like apply_move(), doesn't
+    check for self-check and doesn't toggle state.side_to_move.
+    Returns false if the piece isn't in the pocket.
+    */
+
+    let side = state.side_to_move;
+
+    let pocket = match state.pockets.as_mut() {
+        Some(pockets) => pockets.get_mut(side),
+        None => return false,
+    };
+
+    if !pocket.remove(piece) {
+        return false;
+    }
+
+    let mask: u64 = 1<<square;
+    let board = &mut state.board;
+
+    let bitboard = match (piece.to_ascii_uppercase(), side) {
+        ('P', Color::White) => &mut board.white_pawns,
+        ('P', Color::Black) => &mut board.black_pawns,
+        ('N', Color::White) => &mut board.white_knights,
+        ('N', Color::Black) => &mut board.black_knights,
+        ('B', Color::White) => &mut board.white_bishops,
+        ('B', Color::Black) => &mut board.black_bishops,
+        ('R', Color::White) => &mut board.white_rooks,
+        ('R', Color::Black) => &mut board.black_rooks,
+        (_, Color::White) => &mut board.white_queens,
+        (_, Color::Black) => &mut board.black_queens,
+    };
+    *bitboard |= mask;
+
+    match side {
+        Color::White => board.white_occupied |= mask,
+        Color::Black => board.black_occupied |= mask,
+    }
+
+    if piece.eq_ignore_ascii_case(&'P') {
+        state.halfmove_clock = 0;
+    } else {
+        state.halfmove_clock += 1;
+    }
+    if side == Color::Black {
+        state.fullmove_number += 1;
+    }
+    state.en_passant_mask = 0;
+
+    true
+}
+
+pub fn is_capture(cur_square: i8, target_square: i8, state: &GameState) -> bool {
+
+    // Whether the move from cur_square takes an opponent's piece, including en passant.
+
+    let side = state.side_to_move;
+    let target_mask: u64 = 1<<target_square;
+
+    let takes_piece = matches!(state.board.piece_at(target_square), Some((color, _)) if color != side);
+    let en_passant = state.board.piece_at(cur_square) == Some((side, 'P')) && target_mask == state.en_passant_mask;
+
+    takes_piece || en_passant
+}
+
+pub fn is_promotion(cur_square: i8, target_square: i8, state: &GameState) -> bool {
+
+    let cur_mask: u64 = 1<<cur_square;
+    let target_mask: u64 = 1<<target_square;
+
+    let geometry = &state.board.geometry;
+
+    (cur_mask & state.board.white_pawns != 0 && target_mask & geometry.promotion_rank(Color::White) != 0)
+        || (cur_mask & state.board.black_pawns != 0 && target_mask & geometry.promotion_rank(Color::Black) != 0)
+}
+
+pub fn generate_legal_moves(state: &GameState) -> Vec<Move> {
+
+    /*
+    All moves the side to move can make, including self-check detection. Promotions are listed
+    once per promotion piece.
+    */
+
+    let side = state.side_to_move;
+
+    let mut moves = generate_pseudo_legal_moves(state);
+
+    moves.retain(|mv| {
+        let mut next = state.clone();
+        apply_move(mv.from, mv.to, mv.promotion, &mut next);
+        !is_check(&next, side.opposite())
+    });
+
+    moves
+}
+
+pub fn generate_pseudo_legal_moves(state: &GameState) -> Vec<Move> {
+
+    /*
+    All moves allowed by is_legal(), without self-check detection.
+    */
+
+    let mut moves = Vec::new();
+
+    for cur_square in 0..64 {
+
+        if !read_cur_square(cur_square, state) {
+            continue;
+        }
+
+        for target_square in 0..64 {
+
+            if !is_legal(cur_square, target_square, state) {
+                continue;
+            }
+
+            if is_promotion(cur_square, target_square, state) {
+                for &piece in PROMOTION_PIECES.iter() {
+                    moves.push(Move::with_promotion(cur_square, target_square, piece));
+                }
+            } else {
+                moves.push(Move::new(cur_square, target_square));
+            }
+        }
+    }
+
+    moves
+}
+
 pub fn capture(target_mask: u64, board: &mut Board) -> Option<i8>{
 
     /*
     
-    1 → pawn, 2 → knight, 3 → bishop, 4 → rook, 5 → queen */
+    1 → pawn, 2 → knight, 3 → bishop, 4 → rook, 5 → queen, 6 → king (only in variants where the
+    king can be captured), 7 + i → the fairy piece fairy::FAIRY_PIECES[i] */
 
     if (target_mask & board.black_occupied) != 0 {
 
@@ -241,6 +483,18 @@ pub fn capture(target_mask: u64, board: &mut Board) -> Option<i8>{
             board.black_queens &= !target_mask;
             return Some(5);
         }
+
+        if board.black_king & target_mask != 0 {
+            board.black_king &= !target_mask;
+            return Some(6);
+        }
+
+        for index in 0..FAIRY_COUNT {
+            if board.black_fairy[index] & target_mask != 0 {
+                board.black_fairy[index] &= !target_mask;
+                return Some(7 + index as i8);
+            }
+        }
        
     }
     if (target_mask & board.white_occupied) != 0 {
@@ -270,6 +524,18 @@ pub fn capture(target_mask: u64, board: &mut Board) -> Option<i8>{
             board.white_queens &= !target_mask;
             return Some(5);
         }
+
+        if board.white_king & target_mask != 0 {
+            board.white_king &= !target_mask;
+            return Some(6);
+        }
+
+        for index in 0..FAIRY_COUNT {
+            if board.white_fairy[index] & target_mask != 0 {
+                board.white_fairy[index] &= !target_mask;
+                return Some(7 + index as i8);
+            }
+        }
     }
 
     None
@@ -337,6 +603,19 @@ pub fn fill_square(cur_mask: u64, target_mask: u64, board: &mut Board) {
         board.black_bishops |= target_mask;
     }
 
+    for index in 0..FAIRY_COUNT {
+
+        if (cur_mask & board.white_fairy[index]) != 0 {
+            board.white_occupied |= target_mask;
+            board.white_fairy[index] |= target_mask;
+        }
+
+        if (cur_mask & board.black_fairy[index]) != 0 {
+            board.black_occupied |= target_mask;
+            board.black_fairy[index] |= target_mask;
+        }
+    }
+
 }
 
 pub fn empty_square(cur_mask: u64, board: &mut Board) {
@@ -355,24 +634,22 @@ pub fn empty_square(cur_mask: u64, board: &mut Board) {
     board.black_knights &= !cur_mask;
     board.black_rooks &= !cur_mask;
     board.black_bishops &= !cur_mask;
+
+    for index in 0..FAIRY_COUNT {
+        board.white_fairy[index] &= !cur_mask;
+        board.black_fairy[index] &= !cur_mask;
+    }
 }
 
 pub fn promotion(target_square: i8, state: &mut GameState){
 
-    let target_mask = 1<<target_square;
-
-    let promoted_pawns = match state.side_to_move {
-        Color::White => target_mask & state.board.white_pawns & EIGHT_RANK,
-        Color::Black => target_mask & state.board.black_pawns & FIRST_RANK,
-    };
-
-    if promoted_pawns == 0 {
+    if !is_promoted_pawn(target_square, state) {
         return; // no promoted pawn
     }
 
     loop {
 
-        print!("PROMOTE pawn on square {} to (Q, R, B, N)\n", target_square);
+        println!("PROMOTE pawn on square {} to (Q, R, B, N)", target_square);
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
@@ -380,58 +657,76 @@ pub fn promotion(target_square: i8, state: &mut GameState){
 
         let promotion_piece = input.trim().to_uppercase();
 
-         // Remove the pawn from its bitboard
-        match state.side_to_move {
-            Color::White => state.board.white_pawns &= !target_mask,
-            Color::Black => state.board.black_pawns &= !target_mask,
-        }
-
-        // Add the promoted piece
-        let valid = match promotion_piece.as_str() {
-            "Q" => {
-                match state.side_to_move {
-                    Color::White => state.board.white_queens |= target_mask,
-                    Color::Black =>state.board.black_queens |= target_mask,
-                }
-                true
-            }
-            "R" => {
-                match state.side_to_move {
-                    Color::White => state.board.white_rooks |= target_mask,
-                    Color::Black => state.board.black_rooks |= target_mask,
-                }
-                true
-            }
-            "B" => {
-                match state.side_to_move {
-                    Color::White => state.board.white_bishops |= target_mask,
-                    Color::Black => state.board.black_bishops |= target_mask,
-                }
-                true
-            }
-            "N" => {
-                match state.side_to_move {
-                    Color::White => state.board.white_knights |= target_mask,
-                    Color::Black => state.board.black_knights |= target_mask,
-                }
-                true
-            }
-            _ => {
-                println!("Invalid choice, please enter Q, R, B, or N.");
-                false
-            }
+        let valid = match promotion_piece.chars().next() {
+            Some(piece) if promotion_piece.len() == 1 => promote(target_square, piece, state),
+            _ => false,
         };
 
         if valid {
-                    break;
-                }
+            break;
+        }
+        println!("Invalid choice, please enter Q, R, B, or N.");
+    }
+
+}
+
+fn is_promoted_pawn(target_square: i8, state: &GameState) -> bool {
+
+    let target_mask = 1<<target_square;
+
+    let promoted_pawns = match state.side_to_move {
+        Color::White => target_mask & state.board.white_pawns & state.board.geometry.promotion_rank(Color::White),
+        Color::Black => target_mask & state.board.black_pawns & state.board.geometry.promotion_rank(Color::Black),
+    };
+
+    promoted_pawns != 0
+}
+
+pub fn promote(target_square: i8, piece: char, state: &mut GameState) -> bool {
+
+    /*
+    Replaces the pawn that just reached the last rank on target_square with piece ('Q', 'R', 'B'
+    or 'N'). Returns false if there's no pawn to promote or piece isn't a valid choice.
+    */
+
+    let target_mask = 1<<target_square;
+
+    if !is_promoted_pawn(target_square, state) || !PROMOTION_PIECES.contains(&piece.to_ascii_uppercase()) {
+        return false;
+    }
+
+    let board = &mut state.board;
+
+    // Remove the pawn from its bitboard
+    match state.side_to_move {
+        Color::White => board.white_pawns &= !target_mask,
+        Color::Black => board.black_pawns &= !target_mask,
     }
 
+    // Add the promoted piece
+    let promoted = match (piece.to_ascii_uppercase(), state.side_to_move) {
+        ('Q', Color::White) => &mut board.white_queens,
+        ('Q', Color::Black) => &mut board.black_queens,
+        ('R', Color::White) => &mut board.white_rooks,
+        ('R', Color::Black) => &mut board.black_rooks,
+        ('B', Color::White) => &mut board.white_bishops,
+        ('B', Color::Black) => &mut board.black_bishops,
+        (_, Color::White) => &mut board.white_knights,
+        (_, Color::Black) => &mut board.black_knights,
+    };
+    *promoted |= target_mask;
+
+    true
 }
 
 pub fn en_passant(target_mask: u64, state: &mut GameState) { // checks if the move made was en passant -> update opponent occupied squares
 
-   if target_mask == state.en_passant_mask {
+   let own_pawns = match state.side_to_move {
+       Color::White => state.board.white_pawns,
+       Color::Black => state.board.black_pawns,
+   };
+
+   if target_mask == state.en_passant_mask && (target_mask & own_pawns) != 0 {
     match state.side_to_move {
         Color::White => {
             // capture black's pawn "in front of" the target square
@@ -454,90 +749,121 @@ pub fn update_en_passant_square(cur_square: i8, target_square: i8, state: &mut G
     let cur_mask = 1<<cur_square;
     let target_mask: u64 = 1<<target_square;
 
+    // the en passant square is only available for one move
+    state.en_passant_mask = 0;
 
     if (target_mask & state.board.white_pawns) != 0 {
 
-        if (cur_mask & SECOND_RANK) != 0 && target_square-cur_square==16 {
+        if (cur_mask & state.board.geometry.white_double_push) != 0 && target_square-cur_square==16 {
 
-            state.en_passant_mask = 1<< cur_square+8;
+            state.en_passant_mask = 1 << (cur_square+8);
         }
 
 
-    } else if (target_mask & state.board.black_pawns)!= 0 {
+    } else if (target_mask & state.board.black_pawns)!= 0
         
-        if (cur_mask & SEVENTH_RANK) != 0 && cur_square-target_square==16 {
+        && (cur_mask & state.board.geometry.black_double_push) != 0 && cur_square-target_square==16 {
             
             state.en_passant_mask = 1<<(cur_square-8);
         }
 
-    } else {
-        state.en_passant_mask = 0;
-    }
-
 }
 
-pub fn castle(cur_square: i8, target_square: i8, state: &mut GameState) {
-    
-    //let cur_mask = 1<<cur_square;
-    let target_mask: u64 = 1<<target_square;
+pub fn is_castling_move(cur_square: i8, target_square: i8, state: &GameState) -> bool {
 
-    if (target_mask & state.board.white_king) != 0 {
+    /*
+    Whether moving the king of the side to move from cur_square to target_square is castling:
+    the king is on its start square, the right is still there and the target is the king's final
+    square (standard) or the castling rook's square (Chess960).
+    Doesn't check that castling is allowed right now, that's done by castling() in legal_moves.
+    */
 
-        if cur_square == 4 && target_square == 6 { // king-side castling
+    let side = state.side_to_move;
 
-            // rook on h1 to f1
-            state.board.white_rooks |= 1<<5;
-            state.board.white_rooks &= !(1<<7);
-        }
+    let (king, kingside_right, queenside_right) = match side {
+        Color::White => (state.board.white_king, state.white_can_castle_kingside, state.white_can_castle_queenside),
+        Color::Black => (state.board.black_king, state.black_can_castle_kingside, state.black_can_castle_queenside),
+    };
+
+    if (king & 1<<cur_square) == 0 {
+        return false;
+    }
+
+    for (right, kingside) in [(kingside_right, true), (queenside_right, false)] {
 
-        else if cur_square == 4 && target_square == 2 { // queen-side castling
+        let (king_start, rook_start, king_dest, _) = castling_squares(side, kingside, state);
+        let target = if state.chess960 { rook_start } else { king_dest };
 
-            // rook on a1 to d1
-            state.board.white_rooks |= 1<<3;
-            state.board.white_rooks &= !(1<<0);
+        if right && cur_square == king_start && target_square == target {
+            return true;
         }
+    }
 
+    false
+}
 
-    } else if (target_mask & state.board.black_king) != 0 {
+pub fn castle(cur_square: i8, target_square: i8, state: &mut GameState) {
 
-        if cur_square == 60 && target_square == 62  {// king-side
+    /*
+    Moves the king and the rook for the castling move cur_square -> target_square (see
+    is_castling_move()). Does nothing if it isn't a castling move.
+    */
 
-            // rook on h8 to f8
-            state.board.black_rooks |= 1<<61;
-            state.board.black_rooks &= !(1<<63);
-        }
+    if !is_castling_move(cur_square, target_square, state) {
+        return;
+    }
 
-        else if cur_square == 60 && target_square == 58 { // queen-side
+    let side = state.side_to_move;
+    let kingside = target_square > cur_square;
+    let (king_start, rook_start, king_dest, rook_dest) = castling_squares(side, kingside, state);
 
-            // rook on a8 to d8
-            state.board.black_rooks |= 1<<59;
-            state.board.black_rooks &= !(1<<56);
+    let board = &mut state.board;
 
-        }
-    }
+    let (king, rooks, occupied) = match side {
+        Color::White => (&mut board.white_king, &mut board.white_rooks, &mut board.white_occupied),
+        Color::Black => (&mut board.black_king, &mut board.black_rooks, &mut board.black_occupied),
+    };
+
+    // lift both pieces before placing them, the squares can overlap in Chess960
+    *king &= !(1<<king_start);
+    *rooks &= !(1<<rook_start);
+    *occupied &= !(1<<king_start | 1<<rook_start);
+
+    *king |= 1<<king_dest;
+    *rooks |= 1<<rook_dest;
+    *occupied |= 1<<king_dest | 1<<rook_dest;
 }
 
 pub fn update_castling_rights(cur_square: i8, state: &mut GameState) {
 
-    if cur_square == 0 { // rook on a1
-        state.white_can_castle_queenside = false;
-    }
-    else if cur_square == 4 { // king on e1
-        state.white_can_castle_kingside = false;
-        state.white_can_castle_queenside = false;
-    }
-    else if cur_square == 7 { // rook on h1
-        state.white_can_castle_kingside = false;
-    }
-    else if cur_square == 56 { // rook on a8
-        state.black_can_castle_queenside = false;
-    }
-    else if cur_square == 60 { // king on e8
-        state.black_can_castle_kingside = false;
-        state.black_can_castle_queenside = false;
-    }
-    else if cur_square == 63 { // rook on h8
-        state.black_can_castle_kingside = false;
+    /*
+    Updates castling rights after a piece moves from (or is captured on) cur_square: moving the
+    king loses both rights, moving a castling rook loses the right for that side.
+    */
+
+    for side in [Color::White, Color::Black] {
+
+        let files = state.castling_files(side);
+        let back_rank = match side {
+            Color::White => 0,
+            Color::Black => (state.board.geometry.ranks - 1) * 8,
+        };
+
+        let (kingside, queenside) = match side {
+            Color::White => (&mut state.white_can_castle_kingside, &mut state.white_can_castle_queenside),
+            Color::Black => (&mut state.black_can_castle_kingside, &mut state.black_can_castle_queenside),
+        };
+
+        if cur_square == back_rank + files.king { // king on e1/e8 in standard chess
+            *kingside = false;
+            *queenside = false;
+        }
+        else if cur_square == back_rank + files.kingside_rook { // rook on h1/h8
+            *kingside = false;
+        }
+        else if cur_square == back_rank + files.queenside_rook { // rook on a1/a8
+            *queenside = false;
+        }
     }
 
 
@@ -562,6 +888,10 @@ pub fn checked_squares(state: &GameState, side_checking: Color) -> u64 {
             all_targeted_squares |= rook_moves(board.white_rooks, state, side_checking);
             all_targeted_squares |= queen_moves(board.white_queens, state, side_checking);
             all_targeted_squares |= king_moves(board.white_king, state, side_checking, false);
+
+            for index in 0..FAIRY_COUNT {
+                all_targeted_squares |= fairy_moves(board.white_fairy[index], index, state, side_checking);
+            }
         }
 
         Color::Black => {
@@ -573,10 +903,14 @@ pub fn checked_squares(state: &GameState, side_checking: Color) -> u64 {
             all_targeted_squares |= queen_moves(board.black_queens, state, side_checking);
             all_targeted_squares |= king_moves(board.black_king, state, side_checking, false);
 
+            for index in 0..FAIRY_COUNT {
+                all_targeted_squares |= fairy_moves(board.black_fairy[index], index, state, side_checking);
+            }
+
         }
     }
 
-    return all_targeted_squares;
+    all_targeted_squares
 
 
 }
@@ -593,11 +927,18 @@ pub fn is_check(state: &GameState, side_checking: Color) -> bool {
 
     let board = &state.board;
 
-    match side_checking {
-        Color::White => return checked_squares(state, side_checking) & board.black_king != 0,
-        Color::Black => return checked_squares(state, side_checking) & board.white_king != 0,
+    let king = match side_checking {
+        Color::White => board.black_king,
+        Color::Black => board.white_king,
+    };
+
+    // a side without a king (Horde, Antichess) can't be in check
+    if king == 0 {
+        return false;
     }
 
+    checked_squares(state, side_checking) & king != 0
+
 }
 
 pub fn is_checkmate_stalemate(state: &mut GameState) -> bool {
@@ -617,6 +958,11 @@ pub fn is_checkmate_stalemate(state: &mut GameState) -> bool {
                 continue;
             }
 
+            // castling() only allows castling that doesn't end in check, so it's an escape
+            if is_castling_move(cur_square, target_square, state) {
+                return false;
+            }
+
             // Perform the move in-place and record captured piece
             let cur_mask = 1 << cur_square;
             let target_mask = 1 << target_square;
@@ -627,8 +973,6 @@ pub fn is_checkmate_stalemate(state: &mut GameState) -> bool {
             // move the piece
             fill_square(cur_mask, target_mask, &mut state.board);
             empty_square(cur_mask, &mut state.board);
-            
-            castle(cur_square, target_square, state);
 
 
             // Check if after this move, the side that just moved is NOT in check
@@ -654,7 +998,9 @@ pub fn undo_move(original_square: i8, new_square: i8, state: &mut GameState, sid
     let new_mask = 1<<new_square;
 
     // undo castling
-    undo_castle(original_square, new_square, state);
+    if undo_castle(original_square, new_square, state) {
+        return;
+    }
 
     // fill the original square with the piece.
     fill_square(new_mask, original_mask, &mut state.board);
@@ -673,7 +1019,7 @@ pub fn restore_captured_piece(new_mask: u64, state: &mut GameState, side: Color,
 
     match piece_captured {
 
-        None => return,
+        None => (),
 
         // pawn was captured
         Some(1) => {
@@ -748,48 +1094,79 @@ pub fn restore_captured_piece(new_mask: u64, state: &mut GameState, side: Color,
                 }
             }
         }
-        Some(_) => return,
-    }
-}
 
-pub fn undo_castle(original_square: i8, new_square: i8, state: &mut GameState) {
+        // king was captured
+        Some(6) => {
+            match side {
 
-    let new_mask = 1<<new_square;
+                Color::White => {
+                    board.white_king |= new_mask;
+                    board.white_occupied |= new_mask;
+                }
+                Color::Black => {
+                    board.black_king |= new_mask;
+                    board.black_occupied |= new_mask;
+                }
+            }
+        }
+        // a fairy piece was captured
+        Some(code) if code >= 7 && ((code - 7) as usize) < FAIRY_COUNT => {
 
-    let board = &mut state.board;
+            let index = (code - 7) as usize;
+
+            match side {
 
+                Color::White => {
+                    board.white_fairy[index] |= new_mask;
+                    board.white_occupied |= new_mask;
+                }
+                Color::Black => {
+                    board.black_fairy[index] |= new_mask;
+                    board.black_occupied |= new_mask;
+                }
+            }
+        }
+        Some(_) => (),
+    }
+}
 
-    if new_mask & board.white_king != 0 {
+pub fn undo_castle(original_square: i8, new_square: i8, state: &mut GameState) -> bool {
 
-        if original_square == 4 && new_square == 6 { // kingside
+    /*
+    Reverts the castling move original_square -> new_square (king and rook back on their start
+    squares). Returns false, without changing anything, if the king and rook aren't on the squares
+    that castling would have put them on.
+    */
 
-            // move rook from f1 to h1
-            board.white_rooks &= !(1<<5);
-            board.white_rooks |= 1<<7;
-        }
+    for side in [Color::White, Color::Black] {
 
-        if original_square == 4 && new_square == 2 { // queenside
+        for kingside in [true, false] {
 
-            // move rook from d1 to a1
-            board.white_rooks &= !(1<<3);
-            board.white_rooks |= 1<<0;
-        }
-    }
+            let (king_start, rook_start, king_dest, rook_dest) = castling_squares(side, kingside, state);
+            let target = if state.chess960 { rook_start } else { king_dest };
 
-    else if new_mask & board.black_king != 0 {
+            let board = &mut state.board;
+            let (king, rooks, occupied) = match side {
+                Color::White => (&mut board.white_king, &mut board.white_rooks, &mut board.white_occupied),
+                Color::Black => (&mut board.black_king, &mut board.black_rooks, &mut board.black_occupied),
+            };
 
-        if original_square == 60 && new_square == 62  {// king-side
+            if original_square != king_start || new_square != target
+                || *king & 1<<king_dest == 0 || *rooks & 1<<rook_dest == 0 {
+                continue;
+            }
 
-            // rook on f8 to h8
-            board.black_rooks &= !(1<<61);
-            state.board.black_rooks |= 1<<63;
-        }
+            *king &= !(1<<king_dest);
+            *rooks &= !(1<<rook_dest);
+            *occupied &= !(1<<king_dest | 1<<rook_dest);
 
-        else if original_square == 60 && new_square == 58 { // queen-side
+            *king |= 1<<king_start;
+            *rooks |= 1<<rook_start;
+            *occupied |= 1<<king_start | 1<<rook_start;
 
-            // rook on d8 to a8
-            state.board.black_rooks &= !(1<<59);
-            state.board.black_rooks |= 1<<56;
+            return true;
         }
     }
-}
\ No newline at end of file
+
+    false
+}
@@ -0,0 +1,367 @@
+/*
+Reading and writing games in PGN (Portable Game Notation), https://www.chessprogramming.org/Portable_Game_Notation
+
+A PGN game maps onto a GameTree:
+- tag pairs ([White "Carlsen"]) go into GameTree.tags, the FEN and Variant tags set the start position
+  (and the Variant tag the rules the moves are checked with),
+- moves (SAN) become nodes, the first line is the main line,
+- (...) is a variation: an alternative to the move just before it,
+- {...} is a comment on the move before it (a comment before the first move belongs to the root),
+- $n and the suffixes !, ?, !!, ??, !?, ?! are NAGs on the move before them.
+*/
+
+use crate::fen::{from_fen, FenError};
+use crate::game_tree::{GameTree, NodeId};
+use crate::notation::parse_variant_san;
+use crate::state::{Color, GameState};
+use crate::variant::{from_name, Standard, Variant};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PgnError {
+    UnclosedTag,
+    UnclosedComment,
+    UnmatchedParenthesis,
+    IllegalMove(String),
+    InvalidFen(FenError),
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// the move suffixes with their NAG numbers
+const SUFFIX_NAGS: [(&str, u8); 6] = [("!", 1), ("?", 2), ("!!", 3), ("??", 4), ("!?", 5), ("?!", 6)];
+
+
+// READING
+
+pub fn read_pgn(text: &str) -> Result<GameTree, PgnError> {
+
+    let mut tree = GameTree::new();
+    let mut chars = text.chars().peekable();
+
+    // the node comments and NAGs are attached to, None before the first move of a (sub)line
+    let mut last_move: Option<NodeId> = None;
+    // for every open variation: the cursor and last move to go back to when it closes
+    let mut variation_stack: Vec<(NodeId, Option<NodeId>)> = Vec::new();
+    // a comment at the start of a variation, waiting for the variation's first move
+    let mut pending_comment: Option<String> = None;
+
+    while let Some(&c) = chars.peek() {
+
+        match c {
+
+            '[' => {
+                chars.next();
+                let (name, value) = read_tag(&mut chars).ok_or(PgnError::UnclosedTag)?;
+
+                tree.set_tag(&name, &value);
+
+                // the game starts from the FEN position instead of the standard start position
+                if (name == "FEN" || name == "Variant") && tree.get(tree.root()).children().is_empty() {
+                    let (variant, start) = start_position(&tree)?;
+                    let tags = std::mem::take(&mut tree.tags);
+                    tree = GameTree::with_variant(variant, start);
+                    tree.tags = tags;
+                }
+            }
+
+            '{' => {
+                chars.next();
+                let mut comment = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                if !closed {
+                    return Err(PgnError::UnclosedComment);
+                }
+
+                let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                /* before the first move of a variation the cursor is still on the line it branches
+                from, the comment belongs to the move that comes next */
+                if last_move.is_none() && !variation_stack.is_empty() {
+                    pending_comment = Some(match pending_comment.take() {
+                        Some(pending) => format!("{} {}", pending, comment),
+                        None => comment,
+                    });
+                } else {
+                    let node = last_move.unwrap_or(tree.cursor());
+                    add_comment(&mut tree, node, &comment);
+                }
+            }
+
+            ';' => { // comment to the end of the line
+                chars.by_ref().take_while(|&c| c != '\n').for_each(drop);
+            }
+
+            '(' => {
+                chars.next();
+                variation_stack.push((tree.cursor(), last_move));
+                // the variation replaces the last move, so it starts from the position before it
+                tree.back();
+                last_move = None;
+            }
+
+            ')' => {
+                chars.next();
+                let (cursor, previous_last_move) = variation_stack.pop().ok_or(PgnError::UnmatchedParenthesis)?;
+                tree.go_to(cursor);
+                last_move = previous_last_move;
+
+                // a variation without moves: keep its comment on the move it was meant to replace
+                if let Some(comment) = pending_comment.take() {
+                    add_comment(&mut tree, last_move.unwrap_or(cursor), &comment);
+                }
+            }
+
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+
+            _ => {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "[]{}();".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+
+                if let Some(nag) = token.strip_prefix('$') {
+                    if let (Some(node), Ok(nag)) = (last_move, nag.parse::<u8>()) {
+                        tree.add_nag(node, nag);
+                    }
+                    continue;
+                }
+
+                if RESULTS.contains(&token.as_str()) {
+                    continue;
+                }
+
+                // strip move numbers, eg. "12." "12..." or "12.e4" (digits, then at least one ".",
+                // so castling written with zeros, "0-0", stays a move)
+                let digits = token.len() - token.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+                let san = match token[digits..].strip_prefix('.') {
+                    Some(rest) if digits > 0 => rest.trim_start_matches('.'),
+                    _ => token.as_str(),
+                };
+                if san.is_empty() {
+                    continue;
+                }
+
+                let (san, nag) = split_suffix(san);
+
+                /* a legal move is still refused once the game is over */
+                let mv = parse_variant_san(tree.variant(), san, tree.state()).ok_or_else(|| PgnError::IllegalMove(san.to_string()))?;
+                if !tree.play(mv) {
+                    return Err(PgnError::IllegalMove(san.to_string()));
+                }
+                last_move = Some(tree.cursor());
+
+                if let Some(comment) = pending_comment.take() {
+                    let cursor = tree.cursor();
+                    add_comment(&mut tree, cursor, &comment);
+                }
+                if let Some(nag) = nag {
+                    tree.add_nag(tree.cursor(), nag);
+                }
+            }
+        }
+    }
+
+    if !variation_stack.is_empty() {
+        return Err(PgnError::UnmatchedParenthesis);
+    }
+
+    tree.go_to_start();
+    Ok(tree)
+}
+
+// The variant from the Variant tag (standard chess if it's missing or unknown) and its start position.
+fn start_position(tree: &GameTree) -> Result<(Rc<dyn Variant>, GameState), PgnError> {
+
+    let variant = tree.tag("Variant").and_then(from_name).unwrap_or_else(|| Rc::new(Standard));
+
+    let mut start = match tree.tag("FEN") {
+        Some(fen) => from_fen(fen).map_err(PgnError::InvalidFen)?,
+        None => variant.start_position(),
+    };
+
+    if let Some(variant) = tree.tag("Variant")
+        && (variant.eq_ignore_ascii_case("chess960") || variant.eq_ignore_ascii_case("fischerandom"))
+    {
+        start.chess960 = true;
+    }
+
+    Ok((variant, start))
+}
+
+// Adds comment to the node's comment, after any it already has.
+fn add_comment(tree: &mut GameTree, node: NodeId, comment: &str) {
+    let comment = match &tree.get(node).comment {
+        Some(existing) => format!("{} {}", existing, comment),
+        None => comment.to_string(),
+    };
+    tree.set_comment(node, &comment);
+}
+
+// The name and value of a tag pair, after its "[": the value is quoted ("\"" and "\\" are escapes,
+// a "]" inside it is just text) and the tag has to end with a "]".
+fn read_tag(chars: &mut Peekable<Chars>) -> Option<(String, String)> {
+
+    let skip_whitespace = |chars: &mut Peekable<Chars>| while chars.next_if(|c| c.is_whitespace()).is_some() {};
+
+    skip_whitespace(chars);
+    let mut name = String::new();
+    while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != '"' && c != ']') {
+        name.push(c);
+    }
+
+    skip_whitespace(chars);
+    if name.is_empty() || chars.next() != Some('"') {
+        return None;
+    }
+
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => value.push(chars.next_if(|&c| c == '"' || c == '\\').unwrap_or('\\')),
+            c => value.push(c),
+        }
+    }
+
+    skip_whitespace(chars);
+    if chars.next() != Some(']') {
+        return None;
+    }
+
+    Some((name, value))
+}
+
+fn split_suffix(san: &str) -> (&str, Option<u8>) {
+
+    let end = san.trim_end_matches(['!', '?']).len();
+    let (san, suffix) = san.split_at(end);
+
+    let nag = SUFFIX_NAGS.iter().find(|(text, _)| *text == suffix).map(|&(_, nag)| nag);
+
+    (san, nag)
+}
+
+
+// WRITING
+
+pub fn write_pgn(tree: &GameTree) -> String {
+
+    let mut out = String::new();
+
+    for (name, value) in &tree.tags {
+        out.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    if !tree.tags.is_empty() {
+        out.push('\n');
+    }
+
+    let mut tokens = Vec::new();
+
+    if let Some(comment) = &tree.get(tree.root()).comment {
+        tokens.push(format!("{{{}}}", comment));
+    }
+
+    write_line(tree, tree.root(), &mut tokens, true);
+
+    tokens.push(tree.tag("Result").unwrap_or("*").to_string());
+
+    // wrap lines at 80 characters
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+            out.push_str(&line);
+            out.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    out.push_str(&line);
+    out.push('\n');
+
+    out
+}
+
+fn write_line(tree: &GameTree, id: NodeId, tokens: &mut Vec<String>, mut force_number: bool) {
+
+    /*
+    Writes the moves after node `id`: the main line move, then its variations in parentheses,
+    then the rest of the main line. A black move gets a move number ("5...") when it doesn't
+    directly follow the white move, eg. at the start of a variation or after a comment.
+    */
+
+    let mut id = id;
+
+    loop {
+        let children = tree.get(id).children();
+
+        let main = match children.first() {
+            Some(&main) => main,
+            None => return,
+        };
+
+        write_move(tree, main, tokens, force_number);
+        force_number = false;
+
+        for &variation in &children[1..] {
+            let mut variation_tokens = Vec::new();
+            write_move(tree, variation, &mut variation_tokens, true);
+            write_line(tree, variation, &mut variation_tokens, tree.get(variation).comment.is_some());
+
+            variation_tokens[0].insert(0, '(');
+            variation_tokens.last_mut().unwrap().push(')');
+            tokens.extend(variation_tokens);
+            force_number = true;
+        }
+
+        if tree.get(main).comment.is_some() {
+            force_number = true;
+        }
+
+        id = main;
+    }
+}
+
+fn write_move(tree: &GameTree, id: NodeId, tokens: &mut Vec<String>, force_number: bool) {
+
+    let node = tree.get(id);
+    let before = &tree.get(node.parent().unwrap()).state;
+    let white_moved = before.side_to_move == Color::White;
+    let move_number = before.fullmove_number;
+
+    let mut token = String::new();
+
+    if white_moved {
+        token.push_str(&format!("{}. ", move_number));
+    } else if force_number {
+        token.push_str(&format!("{}... ", move_number));
+    }
+    token.push_str(&node.san);
+    tokens.push(token);
+
+    for nag in &node.nags {
+        tokens.push(format!("${}", nag));
+    }
+
+    if let Some(comment) = &node.comment {
+        tokens.push(format!("{{{}}}", comment));
+    }
+}
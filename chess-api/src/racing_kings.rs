@@ -0,0 +1,83 @@
+/*
+Racing Kings: both kings race to the eighth rank, and the first to get there wins.
+
+- Giving check is illegal, as is moving into check, so no king is ever in check (and there's no
+  checkmate).
+- If white's king reaches the eighth rank first, black gets one more move: if black's king can
+  reach it too, the game continues for that move, and it's a draw if it does.
+- Both sides start on the first two ranks, with no pawns.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{checked_squares, Move};
+use crate::state::{Color, GameState};
+use crate::variant::Variant;
+
+pub const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+
+const EIGHTH_RANK: u64 = 0xFF00000000000000;
+
+pub struct RacingKings;
+
+impl Variant for RacingKings {
+
+    fn name(&self) -> &'static str {
+        "Racing Kings"
+    }
+
+    fn start_position(&self) -> GameState {
+        crate::fen::from_fen(RACING_KINGS_FEN).unwrap()
+    }
+
+    // neither king may be attacked after the move
+    fn is_legal_move(&self, _before: &GameState, after: &GameState, _mv: &Move) -> bool {
+        checked_squares(after, Color::Black) & after.board.white_king == 0
+            && checked_squares(after, Color::White) & after.board.black_king == 0
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        if let Some(outcome) = race_result(state, legal_moves) {
+            return Some(outcome);
+        }
+
+        if legal_moves.is_empty() {
+            return Some(Outcome::draw(Termination::Stalemate));
+        }
+
+        None
+    }
+
+    // a lone king can still win the race
+    fn can_win(&self, _state: &GameState, _side: Color) -> bool {
+        true
+    }
+}
+
+// The result once a king has reached the eighth rank, None while the race is still on (including
+// black's last move to tie, if it has one among legal_moves).
+fn race_result(state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+    let white_home = state.board.white_king & EIGHTH_RANK != 0;
+    let black_home = state.board.black_king & EIGHTH_RANK != 0;
+
+    if white_home && black_home {
+        return Some(Outcome::draw(Termination::KingReachedGoal));
+    }
+    if black_home {
+        return Some(Outcome::win(Color::Black, Termination::KingReachedGoal));
+    }
+    if !white_home {
+        return None;
+    }
+
+    // white got there first, black may still tie
+    let black_can_tie = state.side_to_move == Color::Black && legal_moves.iter()
+        .any(|mv| 1<<mv.to & EIGHTH_RANK != 0 && state.board.black_king & 1<<mv.from != 0);
+
+    if black_can_tie {
+        return None;
+    }
+
+    Some(Outcome::win(Color::White, Termination::KingReachedGoal))
+}
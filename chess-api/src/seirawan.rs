@@ -0,0 +1,156 @@
+/*
+Seirawan chess (S-Chess): each side starts with a hawk (bishop + knight) and an elephant (rook +
+knight) in reserve, kept in GameState.pockets.
+
+- When a back-rank piece moves for the first time, the player may gate a reserve piece onto the
+  square it left (Move.gate). Castling can gate onto the king's or the rook's square.
+- GameState.gating holds the back-rank squares whose piece hasn't moved yet. Once a square's piece
+  has moved or been captured, nothing can be gated there anymore, so a reserve piece can be lost
+  if every back-rank piece moves without gating it.
+- Hawk and elephant are the fairy pieces 'H' and 'E' (fairy::FAIRY_PIECES). Pawns may promote to
+  them too, whether or not they are still in reserve.
+*/
+
+use crate::fairy::fairy_index;
+use crate::legal_moves::castling_squares;
+use crate::perform_moves::{generate_pseudo_legal_moves, is_castling_move, Move};
+use crate::state::{Color, GameState, Pocket, Pockets};
+use crate::variant::{next_state, Variant};
+
+const BACK_RANKS: u64 = 0xFF000000000000FF;
+
+pub struct Seirawan;
+
+impl Variant for Seirawan {
+
+    fn name(&self) -> &'static str {
+        "Seirawan"
+    }
+
+    fn start_position(&self) -> GameState {
+
+        let reserve = Pocket { hawks: 1, elephants: 1, ..Pocket::default() };
+
+        GameState {
+            pockets: Some(Pockets { white: reserve, black: reserve }),
+            gating: BACK_RANKS,
+            ..GameState::new()
+        }
+    }
+
+    // pawns can also promote to a hawk or an elephant, and every move from a square that can gate
+    // also comes with each reserve piece gated (these are checked for legality like any other move,
+    // so a gated piece may block a check)
+    fn extra_moves(&self, state: &GameState) -> Vec<Move> {
+
+        let moves = generate_pseudo_legal_moves(state);
+
+        let mut extra: Vec<Move> = moves.iter()
+            .filter(|mv| mv.promotion == Some('Q'))
+            .flat_map(|mv| Pocket::GATING_PIECES.map(|piece| Move::with_promotion(mv.from, mv.to, piece)))
+            .collect();
+
+        let reserve = match state.pockets {
+            Some(pockets) => *pockets.get(state.side_to_move),
+            None => return extra,
+        };
+
+        for mv in &moves {
+
+            let squares = vacated_squares(state, mv) & state.gating;
+            if squares == 0 {
+                continue;
+            }
+
+            let after = next_state(self, state, mv).board;
+            let empty = !(after.white_occupied | after.black_occupied);
+
+            for square in 0..64 {
+
+                if squares & empty & 1<<square == 0 {
+                    continue;
+                }
+
+                for piece in Pocket::GATING_PIECES {
+                    if reserve.count(piece) > 0 {
+                        extra.push(mv.with_gate(piece, square));
+                    }
+                }
+            }
+        }
+
+        extra
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, mv: &Move) {
+
+        let side = before.side_to_move;
+
+        // promote() only knows the standard pieces, so the pawn is still on the last rank
+        if let Some(piece) = mv.promotion
+            && let Some(index) = fairy_index(piece)
+        {
+            let mask: u64 = 1<<mv.to;
+            let board = &mut after.board;
+
+            match side {
+                Color::White => {
+                    board.white_pawns &= !mask;
+                    board.white_fairy[index] |= mask;
+                }
+                Color::Black => {
+                    board.black_pawns &= !mask;
+                    board.black_fairy[index] |= mask;
+                }
+            }
+        }
+
+        if let Some((piece, square)) = mv.gate {
+
+            let index = fairy_index(piece).unwrap();
+            let mask: u64 = 1<<square;
+            let board = &mut after.board;
+
+            match side {
+                Color::White => {
+                    board.white_fairy[index] |= mask;
+                    board.white_occupied |= mask;
+                }
+                Color::Black => {
+                    board.black_fairy[index] |= mask;
+                    board.black_occupied |= mask;
+                }
+            }
+
+            if let Some(pockets) = after.pockets.as_mut() {
+                pockets.get_mut(side).remove(piece);
+            }
+        }
+
+        // the pieces that moved or were captured can't gate anymore
+        after.gating &= !(vacated_squares(before, mv) | 1<<mv.to);
+
+        // without a reserve left, side's squares don't matter
+        if !after.pockets.is_some_and(|pockets| pockets.get(side).has_gating_pieces()) {
+            after.gating &= match side {
+                Color::White => !0xFF,
+                Color::Black => !(0xFF << 56),
+            };
+        }
+    }
+}
+
+// The squares mv leaves: the king's and the rook's square when castling.
+fn vacated_squares(state: &GameState, mv: &Move) -> u64 {
+
+    if mv.drop.is_some() {
+        return 0;
+    }
+
+    if is_castling_move(mv.from, mv.to, state) {
+        let (king, rook, _, _) = castling_squares(state.side_to_move, mv.to > mv.from, state);
+        return 1<<king | 1<<rook;
+    }
+
+    1<<mv.from
+}
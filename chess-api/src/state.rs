@@ -9,12 +9,12 @@ Allows you to manage the game state cleanly without relying on eg. static mut (t
 */
 
 use crate::bitboards::Board;
-use std::array;
+use crate::fairy::FAIRY_COUNT;
+use crate::legal_moves::pawn_moves;
+use std::collections::VecDeque;
 
-const MAX_GAME_MOVES: usize = 200;
 
-
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Color {
     White,
     Black,
@@ -32,6 +32,129 @@ impl Color {
 
 
 
+// Files (0 = a, 7 = h) the king and the castling rooks start on. Always e, h and a in standard
+// chess, but they differ between start positions in Chess960.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingFiles {
+    pub king: i8,
+    pub kingside_rook: i8,
+    pub queenside_rook: i8,
+}
+
+impl CastlingFiles {
+    pub const STANDARD: CastlingFiles = CastlingFiles { king: 4, kingside_rook: 7, queenside_rook: 0 };
+}
+
+// Checks given by each side, counted in Three-check.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct CheckCounter {
+    pub white: u8,
+    pub black: u8,
+}
+
+impl CheckCounter {
+    pub fn given_by(&self, side: Color) -> u8 {
+        match side {
+            Color::White => self.white,
+            Color::Black => self.black,
+        }
+    }
+}
+
+// Pieces in hand (Crazyhouse), that can be dropped onto the board instead of making a move.
+// S-Chess keeps its reserve hawk and elephant here too.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Pocket {
+    pub pawns: u8,
+    pub knights: u8,
+    pub bishops: u8,
+    pub rooks: u8,
+    pub queens: u8,
+    pub hawks: u8,
+    pub elephants: u8,
+}
+
+impl Pocket {
+
+    pub const PIECES: [char; 5] = ['Q', 'R', 'B', 'N', 'P'];
+    pub const GATING_PIECES: [char; 2] = ['H', 'E']; // S-Chess: hawk and elephant
+
+    pub fn count(&self, piece: char) -> u8 {
+        match piece.to_ascii_uppercase() {
+            'P' => self.pawns,
+            'N' => self.knights,
+            'B' => self.bishops,
+            'R' => self.rooks,
+            'Q' => self.queens,
+            'H' => self.hawks,
+            'E' => self.elephants,
+            _ => 0,
+        }
+    }
+
+    fn slot(&mut self, piece: char) -> Option<&mut u8> {
+        match piece.to_ascii_uppercase() {
+            'P' => Some(&mut self.pawns),
+            'N' => Some(&mut self.knights),
+            'B' => Some(&mut self.bishops),
+            'R' => Some(&mut self.rooks),
+            'Q' => Some(&mut self.queens),
+            'H' => Some(&mut self.hawks),
+            'E' => Some(&mut self.elephants),
+            _ => None,
+        }
+    }
+
+    // Kings can't go into a pocket, adding one does nothing.
+    pub fn add(&mut self, piece: char) {
+        if let Some(count) = self.slot(piece) {
+            *count += 1;
+        }
+    }
+
+    // Returns false if there's no such piece in the pocket.
+    pub fn remove(&mut self, piece: char) -> bool {
+        match self.slot(piece) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Pocket::default()
+    }
+
+    pub fn has_gating_pieces(&self) -> bool {
+        self.hawks + self.elephants > 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Pockets {
+    pub white: Pocket,
+    pub black: Pocket,
+}
+
+impl Pockets {
+
+    pub fn get(&self, side: Color) -> &Pocket {
+        match side {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    pub fn get_mut(&mut self, side: Color) -> &mut Pocket {
+        match side {
+            Color::White => &mut self.white,
+            Color::Black => &mut self.black,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct GameState {
     pub board: Board,
@@ -39,62 +162,291 @@ pub struct GameState {
     pub white_can_castle_queenside: bool,
     pub black_can_castle_kingside: bool,
     pub black_can_castle_queenside: bool,
+    pub white_castling_files: CastlingFiles,
+    pub black_castling_files: CastlingFiles,
+    pub chess960: bool, // castling is written as the king moving onto its own rook
     pub en_passant_mask: u64, 
     pub side_to_move: Color,
+    pub halfmove_clock: u32, // half-moves since the last capture or pawn move (fifty-move rule)
+    pub fullmove_number: u32, // starts at 1, incremented after black's move
+    pub checks: Option<CheckCounter>, // None unless the variant counts checks
+    pub pockets: Option<Pockets>, // None unless the variant has pieces in hand
+    pub promoted: u64, // pieces that were promoted from pawns, they go into a pocket as pawns again
+    pub gating: u64, // S-Chess: back-rank squares whose piece hasn't moved yet, a reserve piece can be gated there
 }
 
 impl GameState {
     pub fn new() -> Self {
+        Self::with_board(Board::new())
+    }
+
+    // White to move from any setup of the pieces. Castling is allowed for the sides whose king and
+    // rooks are on their standard squares.
+    pub fn with_board(board: Board) -> Self {
+
+        let white_king = board.white_king & 1<<4 != 0;
+        let black_king = board.black_king & 1<<60 != 0;
+
         Self {
-            board: Board::new(),
-            white_can_castle_kingside: true,
-            white_can_castle_queenside: true,
-            black_can_castle_kingside: true,
-            black_can_castle_queenside: true,
+            white_can_castle_kingside: white_king && board.white_rooks & 1<<7 != 0,
+            white_can_castle_queenside: white_king && board.white_rooks & 1<<0 != 0,
+            black_can_castle_kingside: black_king && board.black_rooks & 1<<63 != 0,
+            black_can_castle_queenside: black_king && board.black_rooks & 1<<56 != 0,
+            board,
+            white_castling_files: CastlingFiles::STANDARD,
+            black_castling_files: CastlingFiles::STANDARD,
+            chess960: false,
             en_passant_mask: 0,
             side_to_move: Color::White,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            checks: None,
+            pockets: None,
+            promoted: 0,
+            gating: 0,
+        }
+    }
+
+    // Chess960 (Fischer Random) start position number `index` (0-959), using Scharnagl's
+    // numbering (https://www.chessprogramming.org/Reinhard_Scharnagl). 518 is the standard position.
+    pub fn new_chess960(index: u16) -> Option<Self> {
+
+        if index > 959 {
+            return None;
+        }
+
+        // knight placements among the 5 squares left after bishops and queen
+        const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+        let mut n = index as usize;
+        let mut back_rank = [' '; 8];
+
+        back_rank[(n % 4) * 2 + 1] = 'B'; // light-squared bishop
+        n /= 4;
+        back_rank[(n % 4) * 2] = 'B'; // dark-squared bishop
+        n /= 4;
+
+        let empty = |rank: &[char; 8]| (0..8).filter(|&f| rank[f] == ' ').collect::<Vec<usize>>();
+
+        back_rank[empty(&back_rank)[n % 6]] = 'Q';
+        n /= 6;
+
+        let (first, second) = KNIGHTS[n];
+        let free = empty(&back_rank);
+        back_rank[free[first]] = 'N';
+        back_rank[free[second]] = 'N';
+
+        // the king goes between the rooks on the last three squares
+        let free = empty(&back_rank);
+        back_rank[free[0]] = 'R';
+        back_rank[free[1]] = 'K';
+        back_rank[free[2]] = 'R';
+
+        let mut board = Board::empty();
+        board.white_pawns = 0xFF00;
+        board.black_pawns = 0xFF << 48;
+
+        for (file, piece) in back_rank.iter().enumerate() {
+            let white: u64 = 1 << file;
+            let black: u64 = 1 << (56 + file);
+            let (white_pieces, black_pieces) = match piece {
+                'R' => (&mut board.white_rooks, &mut board.black_rooks),
+                'N' => (&mut board.white_knights, &mut board.black_knights),
+                'B' => (&mut board.white_bishops, &mut board.black_bishops),
+                'Q' => (&mut board.white_queens, &mut board.black_queens),
+                _ => (&mut board.white_king, &mut board.black_king),
+            };
+            *white_pieces |= white;
+            *black_pieces |= black;
+        }
+        board.white_occupied = 0xFFFF;
+        board.black_occupied = 0xFFFF << 48;
+
+        let files = CastlingFiles {
+            king: free[1] as i8,
+            kingside_rook: free[2] as i8,
+            queenside_rook: free[0] as i8,
+        };
+
+        Some(Self {
+            board,
+            white_castling_files: files,
+            black_castling_files: files,
+            chess960: true,
+            ..GameState::new()
+        })
+    }
+
+    pub fn castling_files(&self, side: Color) -> CastlingFiles {
+        match side {
+            Color::White => self.white_castling_files,
+            Color::Black => self.black_castling_files,
         }
     }
 
     pub fn reset(&mut self) {
         *self = GameState::new();
     }
+
+    // Identifies the position for repetition detection: same pieces on the same squares, same
+    // side to move, same castling rights, same en passant possibility, same checks given and same
+    // pieces in hand.
+    pub fn repetition_key(&self) -> RepetitionKey {
+
+        let board = &self.board;
+
+        // the en passant square only matters if a pawn can actually capture there
+        let en_passant_pawns = match self.side_to_move {
+            Color::White => pawn_moves(board.white_pawns, self, Color::White),
+            Color::Black => pawn_moves(board.black_pawns, self, Color::Black),
+        };
+
+        RepetitionKey {
+            pieces: [
+                board.white_pawns, board.white_knights, board.white_bishops,
+                board.white_rooks, board.white_queens, board.white_king,
+                board.black_pawns, board.black_knights, board.black_bishops,
+                board.black_rooks, board.black_queens, board.black_king,
+            ],
+            side_to_move: self.side_to_move,
+            castling: [
+                self.white_can_castle_kingside, self.white_can_castle_queenside,
+                self.black_can_castle_kingside, self.black_can_castle_queenside,
+            ],
+            en_passant_mask: self.en_passant_mask & en_passant_pawns,
+            checks: self.checks,
+            pockets: self.pockets,
+            promoted: self.promoted,
+            duck: board.duck,
+            fairy: [board.white_fairy, board.black_fairy],
+            gating: self.gating,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct RepetitionKey {
+    pieces: [u64; 12],
+    side_to_move: Color,
+    castling: [bool; 4],
+    en_passant_mask: u64,
+    checks: Option<CheckCounter>,
+    pockets: Option<Pockets>,
+    promoted: u64,
+    duck: u64,
+    fairy: [[u64; FAIRY_COUNT]; 2],
+    gating: u64,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::new()
+    }
 }
 
 
 // Game history inspired by: https://rustic-chess.org/board_representation/game_history.html
 
+/*
+History stores the game state from before every move, so undoing a move is just popping the
+last state. The list grows as needed. With a retention limit only the latest states are kept,
+the oldest ones are dropped (counted in `dropped`, so ply numbers stay absolute).
+
+Undone states are put on a redo stack, which lets a UI step forward again. Pushing a new
+state (making a new move) clears the redo stack, since the undone moves no longer follow.
+*/
 
 pub struct History {
-    list: [GameState; MAX_GAME_MOVES],
-    count: usize,
+    list: VecDeque<GameState>,
+    redo_list: Vec<GameState>,
+    max_len: Option<usize>,
+    dropped: usize,
 }
 
 impl History {
-    // Create a new history array containing game states.
+    // Create a new, unbounded history.
     pub fn new() -> Self {
         Self {
-            list: array::from_fn(|_| GameState::new()),
-            count: 0,
+            list: VecDeque::new(),
+            redo_list: Vec::new(),
+            max_len: None,
+            dropped: 0,
         }
     }
 
-    // Put a new game state into the array.
+    // Create a history that only keeps the latest max_len game states.
+    pub fn with_retention(max_len: usize) -> Self {
+        Self {
+            max_len: Some(max_len),
+            ..History::new()
+        }
+    }
+
+    // Put a new game state into the history. Clears the redo stack.
     pub fn push(&mut self, g: GameState) {
-        self.list[self.count] = g;
-        self.count += 1;
+        self.list.push_back(g);
+        self.redo_list.clear();
+        self.trim();
     }
 
-    // Return the last game state and decrement the counter. The game state is
-    // not deleted from the array. If necessary, another game state will just
-    // overwrite it.
+    // Drop the oldest states beyond max_len.
+    fn trim(&mut self) {
+        if let Some(max_len) = self.max_len {
+            while self.list.len() > max_len {
+                self.list.pop_front();
+                self.dropped += 1;
+            }
+        }
+    }
+
+    // Return the last game state and remove it from the history. The redo stack is not touched.
     pub fn pop(&mut self) -> Option<GameState> {
-        if self.count > 0 {
-            self.count -= 1;
-            Some(self.list[self.count].clone()) // must clone since GameState isn't Copy
-        } else {
-            None
+        self.list.pop_back()
+    }
+
+    // Undo the last move: returns the previous game state and saves `current` so it can be redone.
+    pub fn undo(&mut self, current: GameState) -> Option<GameState> {
+        let prev = self.list.pop_back()?;
+        self.redo_list.push(current);
+        Some(prev)
+    }
+
+    // Redo the last undone move: returns the game state after that move and saves `current`
+    // as the state before it.
+    pub fn redo(&mut self, current: GameState) -> Option<GameState> {
+        let next = self.redo_list.pop()?;
+        self.list.push_back(current);
+        self.trim();
+        Some(next)
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_list.is_empty()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo_list.len()
+    }
+
+    // Go back to the position before move number `ply` (0 = start position) to branch off from it.
+    // Every later state and the redo stack are dropped. Returns the game state to continue from,
+    // or None if that ply is not in the (retained) history.
+    pub fn truncate_to(&mut self, ply: usize) -> Option<GameState> {
+        if ply < self.dropped || ply >= self.ply() {
+            return None;
         }
+
+        let index = ply - self.dropped;
+        let state = self.list[index].clone();
+        self.list.truncate(index);
+        self.redo_list.clear();
+
+        Some(state)
+    }
+
+    // Number of moves made, including dropped ones.
+    pub fn ply(&self) -> usize {
+        self.dropped + self.list.len()
     }
 
     pub fn get_ref(&self, index: usize) -> &GameState {
@@ -102,10 +454,22 @@ impl History {
     }
 
     pub fn len(&self) -> usize {
-        self.count
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
     }
 
     pub fn clear(&mut self) {
-        self.count = 0;
+        self.list.clear();
+        self.redo_list.clear();
+        self.dropped = 0;
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,74 @@
+/*
+Three-check: standard chess, but a player also wins by giving check for the third time.
+
+The checks each side has given are counted in GameState.checks, so they are part of the FEN
+("+N+M"), of the repetition key, and of every position saved in History (undoing a move also
+takes back its check).
+Checkmate still wins, and only bare kings are a draw by insufficient material, since any other
+piece can still give checks.
+*/
+
+use crate::outcome::{Outcome, Termination};
+use crate::perform_moves::{is_check, Move};
+use crate::state::{CheckCounter, Color, GameState};
+use crate::variant::Variant;
+
+pub const CHECKS_TO_WIN: u8 = 3;
+
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn start_position(&self) -> GameState {
+        GameState {
+            checks: Some(CheckCounter::default()),
+            ..GameState::new()
+        }
+    }
+
+    fn after_move(&self, before: &GameState, after: &mut GameState, _mv: &Move) {
+
+        let side = before.side_to_move;
+        let mut checks = after.checks.unwrap_or_default();
+
+        if is_check(after, side) {
+            match side {
+                Color::White => checks.white += 1,
+                Color::Black => checks.black += 1,
+            }
+        }
+
+        after.checks = Some(checks);
+    }
+
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+        let side = state.side_to_move;
+
+        if checks_given(state, side.opposite()) >= CHECKS_TO_WIN {
+            return Some(Outcome::win(side.opposite(), Termination::ThreeChecks));
+        }
+
+        if legal_moves.is_empty() {
+            if is_check(state, side.opposite()) {
+                return Some(Outcome::win(side.opposite(), Termination::Checkmate));
+            }
+            return Some(Outcome::draw(Termination::Stalemate));
+        }
+
+        let board = &state.board;
+        if board.white_occupied == board.white_king && board.black_occupied == board.black_king {
+            return Some(Outcome::draw(Termination::InsufficientMaterial));
+        }
+
+        None
+    }
+}
+
+fn checks_given(state: &GameState, side: Color) -> u8 {
+    state.checks.map_or(0, |checks| checks.given_by(side))
+}
@@ -0,0 +1,189 @@
+/*
+Rule sets (variants) that change how chess is played.
+
+A Variant only describes what is different from standard chess, through hooks that all default to
+the standard rules:
+- start_position: the position a game starts from
+- extra_moves: pseudo-legal moves on top of the standard ones (eg. dropping a piece)
+- in_check, is_legal_move / filter_moves: which of the pseudo-legal moves are legal
+- after_move: anything that happens after a move (eg. pieces exploding)
+- outcome: when the game is over, and who won
+- can_win: whether a side could still win, which decides if running out of time loses
+
+The functions at the bottom of this file (legal_moves, next_state, make_move, outcome) drive a game
+through these hooks. Game and GameTree use them, the free function perform_moves::make_move() always
+plays standard chess.
+*/
+
+use crate::antichess::Antichess;
+use crate::atomic::Atomic;
+use crate::bughouse::BughouseBoard;
+use crate::crazyhouse::Crazyhouse;
+use crate::duck_chess::DuckChess;
+use crate::fog_of_war::FogOfWar;
+use crate::horde::Horde;
+use crate::king_of_the_hill::KingOfTheHill;
+use crate::minichess::{Gardner, LosAlamos};
+use crate::outcome::{can_checkmate, is_insufficient_material, Outcome, Termination};
+use crate::perform_moves::{apply_drop, apply_move, generate_pseudo_legal_moves, is_check, Move};
+use crate::racing_kings::RacingKings;
+use crate::seirawan::Seirawan;
+use crate::state::{Color, GameState, History};
+use crate::three_check::ThreeCheck;
+use std::rc::Rc;
+
+pub trait Variant {
+
+    // The name used in the PGN Variant tag, eg. "King of the Hill".
+    fn name(&self) -> &'static str;
+
+    fn start_position(&self) -> GameState {
+        GameState::new()
+    }
+
+    // Pseudo-legal moves the standard rules don't know about.
+    fn extra_moves(&self, _state: &GameState) -> Vec<Move> {
+        Vec::new()
+    }
+
+    // Whether side's king is in check.
+    fn in_check(&self, state: &GameState, side: Color) -> bool {
+        is_check(state, side.opposite())
+    }
+
+    // Whether mv, which led from `before` to `after`, may be played. The standard rule is that the
+    // mover's king can't be left in check.
+    fn is_legal_move(&self, before: &GameState, after: &GameState, _mv: &Move) -> bool {
+        !self.in_check(after, before.side_to_move)
+    }
+
+    // A last pass over all legal moves, for rules that depend on the other moves (eg. captures
+    // being compulsory).
+    fn filter_moves(&self, _state: &GameState, moves: Vec<Move>) -> Vec<Move> {
+        moves
+    }
+
+    // Called after mv has been applied and the turn has passed to the opponent.
+    fn after_move(&self, _before: &GameState, _after: &mut GameState, _mv: &Move) {}
+
+    // Whether the game is over in `state`, given the legal moves of the side to move.
+    fn outcome(&self, state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+        standard_outcome(state, legal_moves)
+    }
+
+    // Whether side could still win with any series of moves. A player who runs out of time only
+    // loses if the opponent can.
+    fn can_win(&self, state: &GameState, side: Color) -> bool {
+        can_checkmate(&state.board, side)
+    }
+}
+
+// Checkmate, stalemate and insufficient material.
+pub fn standard_outcome(state: &GameState, legal_moves: &[Move]) -> Option<Outcome> {
+
+    let side = state.side_to_move;
+
+    if legal_moves.is_empty() {
+        if is_check(state, side.opposite()) {
+            return Some(Outcome::win(side.opposite(), Termination::Checkmate));
+        }
+        return Some(Outcome::draw(Termination::Stalemate));
+    }
+
+    if is_insufficient_material(&state.board) {
+        return Some(Outcome::draw(Termination::InsufficientMaterial));
+    }
+
+    None
+}
+
+// Standard chess (Chess960 is standard chess with GameState.chess960 set).
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+}
+
+// The variant for a PGN Variant tag, None if it isn't known.
+pub fn from_name(name: &str) -> Option<Rc<dyn Variant>> {
+
+    let name = name.to_ascii_lowercase();
+
+    match name.as_str() {
+        "standard" | "chess" | "chess960" | "fischerandom" => Some(Rc::new(Standard)),
+        "king of the hill" | "kingofthehill" | "koth" => Some(Rc::new(KingOfTheHill)),
+        "three-check" | "threecheck" | "3-check" => Some(Rc::new(ThreeCheck)),
+        "atomic" => Some(Rc::new(Atomic)),
+        "antichess" | "losing chess" | "giveaway" => Some(Rc::new(Antichess)),
+        "crazyhouse" => Some(Rc::new(Crazyhouse)),
+        "horde" => Some(Rc::new(Horde)),
+        "racing kings" | "racingkings" => Some(Rc::new(RacingKings)),
+        "bughouse" => Some(Rc::new(BughouseBoard)),
+        "fog of war" | "fogofwar" | "dark chess" => Some(Rc::new(FogOfWar)),
+        "duck chess" | "duckchess" | "duck" => Some(Rc::new(DuckChess)),
+        "seirawan" | "s-chess" | "schess" => Some(Rc::new(Seirawan)),
+        "los alamos" | "losalamos" => Some(Rc::new(LosAlamos)),
+        "gardner" | "minichess" => Some(Rc::new(Gardner)),
+        _ => None,
+    }
+}
+
+
+// DRIVING A GAME
+
+pub fn legal_moves(variant: &dyn Variant, state: &GameState) -> Vec<Move> {
+
+    let mut moves = generate_pseudo_legal_moves(state);
+    moves.extend(variant.extra_moves(state));
+
+    moves.retain(|mv| variant.is_legal_move(state, &next_state(variant, state, mv), mv));
+
+    variant.filter_moves(state, moves)
+}
+
+// The position after mv, with the turn passed to the opponent. mv isn't checked for legality.
+pub fn next_state(variant: &dyn Variant, state: &GameState, mv: &Move) -> GameState {
+
+    let mut next = state.clone();
+
+    match mv.drop {
+        Some(piece) => { apply_drop(piece, mv.to, &mut next); }
+        None => apply_move(mv.from, mv.to, mv.promotion, &mut next),
+    }
+    next.side_to_move = state.side_to_move.opposite();
+
+    variant.after_move(state, &mut next, mv);
+
+    next
+}
+
+// The moves that can still be played in state: the legal moves, none once the game is over.
+pub fn playable_moves(variant: &dyn Variant, state: &GameState) -> Vec<Move> {
+
+    let moves = legal_moves(variant, state);
+
+    if variant.outcome(state, &moves).is_some() {
+        return Vec::new();
+    }
+    moves
+}
+
+// Like perform_moves::make_move(), for any variant: plays mv if it's legal and the game isn't over,
+// saving the previous position in history.
+pub fn make_move(variant: &dyn Variant, mv: &Move, state: &mut GameState, history: &mut History) -> bool {
+
+    if !playable_moves(variant, state).contains(mv) {
+        return false;
+    }
+
+    let next = next_state(variant, state, mv);
+    history.push(std::mem::replace(state, next));
+
+    true
+}
+
+pub fn outcome(variant: &dyn Variant, state: &GameState) -> Option<Outcome> {
+    variant.outcome(state, &legal_moves(variant, state))
+}
@@ -1,5 +1,6 @@
 
 use crate::bitboards::Board;
+use crate::fairy::FAIRY_PIECES;
 
 
 // FOLLOWING CODE ONLY USED TO PRINT (VISUALIZE) THE BOARD
@@ -17,36 +18,56 @@ pub fn print_board(board: &Board) {
     /*Represent pieces as colorPIECETYPE, w for white / b for black, and uppercase first letter of piece name
     (exception: knight, which gets uppercase N, since K is used by king.) */
 
-    bitboard_to_piecetype.insert(u64::from(board.white_pawns), String::from("wP"));
-    bitboard_to_piecetype.insert(u64::from(board.white_rooks), String::from("wR"));
-    bitboard_to_piecetype.insert(u64::from(board.white_knights), String::from("wN"));
-    bitboard_to_piecetype.insert(u64::from(board.white_bishops), String::from("wB"));
-    bitboard_to_piecetype.insert(u64::from(board.white_queens), String::from("wQ"));
-    bitboard_to_piecetype.insert(u64::from(board.white_king), String::from("wK"));
-    bitboard_to_piecetype.insert(u64::from(board.black_pawns), String::from("bP"));
-    bitboard_to_piecetype.insert(u64::from(board.black_rooks), String::from("bR"));
-    bitboard_to_piecetype.insert(u64::from(board.black_knights), String::from("bN"));
-    bitboard_to_piecetype.insert(u64::from(board.black_bishops), String::from("bB"));
-    bitboard_to_piecetype.insert(u64::from(board.black_queens), String::from("bQ"));
-    bitboard_to_piecetype.insert(u64::from(board.black_king), String::from("bK"));
+    bitboard_to_piecetype.insert(board.white_pawns, String::from("wP"));
+    bitboard_to_piecetype.insert(board.white_rooks, String::from("wR"));
+    bitboard_to_piecetype.insert(board.white_knights, String::from("wN"));
+    bitboard_to_piecetype.insert(board.white_bishops, String::from("wB"));
+    bitboard_to_piecetype.insert(board.white_queens, String::from("wQ"));
+    bitboard_to_piecetype.insert(board.white_king, String::from("wK"));
+    bitboard_to_piecetype.insert(board.black_pawns, String::from("bP"));
+    bitboard_to_piecetype.insert(board.black_rooks, String::from("bR"));
+    bitboard_to_piecetype.insert(board.black_knights, String::from("bN"));
+    bitboard_to_piecetype.insert(board.black_bishops, String::from("bB"));
+    bitboard_to_piecetype.insert(board.black_queens, String::from("bQ"));
+    bitboard_to_piecetype.insert(board.black_king, String::from("bK"));
 
 
     let mut entire_board: [String; 64] = std::array::from_fn(|_| ".".to_string());
 
     // fill the board
 
-    for i in 0..12 {
+    for bitboard in boards {
 
-        for j in 0..64 {
+        for (j, square) in entire_board.iter_mut().enumerate() {
 
-            if (boards[i] >> j & 1) != 0 { // checks if boards[i] is occupied
+            if (bitboard >> j & 1) != 0 { // checks if bitboard is occupied
  
-                entire_board[j] = bitboard_to_piecetype.get(&boards[i]).unwrap().to_owned(); // &Option<String> to String https://stackoverflow.com/questions/72840654/get-string-from-optionstring-in-rust
+                *square = bitboard_to_piecetype.get(&bitboard).unwrap().to_owned(); // &Option<String> to String https://stackoverflow.com/questions/72840654/get-string-from-optionstring-in-rust
             }
         }
     }
 
 
+    // fairy pieces, by their letter
+    for (index, piece) in FAIRY_PIECES.iter().enumerate() {
+        for (j, square) in entire_board.iter_mut().enumerate() {
+            if (board.white_fairy[index] >> j & 1) != 0 {
+                *square = format!("w{}", piece.letter);
+            }
+            if (board.black_fairy[index] >> j & 1) != 0 {
+                *square = format!("b{}", piece.letter);
+            }
+        }
+    }
+
+    // the duck (Duck chess)
+    for (j, square) in entire_board.iter_mut().enumerate() {
+        if (board.duck >> j & 1) != 0 {
+            *square = String::from("D");
+        }
+    }
+
+
     // print the board
     /* The first square to be printed should be a8, so we get
     a8 b8 ... h8
@@ -58,16 +79,17 @@ pub fn print_board(board: &Board) {
         for j in 0..8 { // print left to right
             print!("{} ",entire_board[i*8+j]);
         }
-        print!("{}", "\n\n");
+        print!("\n\n");
     
 
     }
-    print!("{}", "\n\n");
+    print!("\n\n");
 
 }
 
 
 #[allow(dead_code)] // (only used in tests)
+#[allow(clippy::precedence, clippy::print_literal)]
 fn dbg_print_board(bb: u64) { // for debugging and testing
 
      // print the board